@@ -1,4 +1,7 @@
-#[derive(Debug, Default, Eq, PartialEq)]
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub struct Amount {
     pub commodity: String,
     pub quantity: i64,
@@ -12,13 +15,89 @@ impl Amount {
             commodity: commodity.to_owned(),
         }
     }
+
+    /// The quantity as a decimal without the commodity, e.g. "-40.00"
+    pub fn quantity_string(&self) -> String {
+        let sign = if self.quantity < 0 { "-" } else { "" };
+        let quantity = self.quantity.unsigned_abs();
+        format!("{}{}.{:02}", sign, quantity / 100, quantity % 100)
+    }
+
+    /// Parses a quantity written by `quantity_string`, or a whole number
+    pub fn parse_quantity(s: &str) -> Option<i64> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (whole, fraction) = match s.split_once('.') {
+            Some((whole, fraction)) if fraction.len() == 2 => (whole, fraction),
+            Some(_) => return None,
+            None => (s, "00"),
+        };
+        if whole.is_empty() || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let quantity = whole.parse::<i64>().ok()?.checked_mul(100)? + fraction.parse::<i64>().ok()?;
+        Some(if negative { -quantity } else { quantity })
+    }
 }
 
 // TOOD custom impl for adding
 
+/// Serialises to `{"commodity": string, "quantity": string}` where the quantity is a decimal with two places
+/// so that it round-trips without floating point error
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Amount", 2)?;
+        state.serialize_field("commodity", &self.commodity)?;
+        state.serialize_field("quantity", &self.quantity_string())?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Serialised {
+            commodity: String,
+            quantity: String,
+        }
+
+        let amount = Serialised::deserialize(deserializer)?;
+        let quantity = Amount::parse_quantity(&amount.quantity)
+            .ok_or_else(|| de::Error::custom(format!("invalid quantity: {}", amount.quantity)))?;
+        Ok(Amount::new(quantity, &amount.commodity))
+    }
+}
+
 impl std::fmt::Display for Amount {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let quantity = self.quantity as f64 / 100.;
-        write!(f, "{}{:.2}", self.commodity, quantity)
+        write!(f, "{}{}", self.commodity, self.quantity_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_formats_quantities() {
+        assert_eq!(Amount::new(-4000, "£").to_string(), "£-40.00");
+        assert_eq!(Amount::new(-5, "£").quantity_string(), "-0.05");
+        assert_eq!(
+            Amount::new(123456789012345678, "").quantity_string(),
+            "1234567890123456.78"
+        );
+    }
+
+    #[test]
+    fn it_parses_quantities() {
+        assert_eq!(Amount::parse_quantity("-40.00"), Some(-4000));
+        assert_eq!(Amount::parse_quantity("-0.05"), Some(-5));
+        assert_eq!(Amount::parse_quantity("12"), Some(1200));
+        assert_eq!(Amount::parse_quantity("1.5"), None);
+        assert_eq!(Amount::parse_quantity("-"), None);
+        assert_eq!(Amount::parse_quantity("£1.00"), None);
     }
 }
@@ -0,0 +1,190 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tree::Tree;
+
+use super::{Payee, PeriodicTransaction, Posting, Price, Transaction};
+
+/// Everything read from a set of journal files
+///
+/// Transactions and postings refer to each other by their indexes in `transactions()` and `postings()`, which
+/// `postings_for` and `transaction_of` follow.
+pub struct Journal {
+    transactions: Vec<Transaction>,
+    postings: Vec<Posting>,
+    /// Each account's value is the indexes of the postings to it
+    accounts: Tree<Vec<usize>>,
+    commodities: BTreeSet<String>,
+    prices: Vec<Price>,
+    payees: Vec<Payee>,
+    periodic_transactions: Vec<PeriodicTransaction>,
+    /// The number of transactions in each source
+    sources: HashMap<PathBuf, usize>,
+    read_time: Duration,
+}
+
+impl Journal {
+    /// Builds the accounts and commodities from the postings
+    pub fn new(
+        transactions: Vec<Transaction>,
+        postings: Vec<Posting>,
+        sources: HashMap<PathBuf, usize>,
+        payees: Vec<Payee>,
+        prices: Vec<Price>,
+    ) -> Self {
+        let mut accounts: Tree<Vec<usize>> = Tree::new();
+        let mut commodities = BTreeSet::new();
+
+        for (index, posting) in postings.iter().enumerate() {
+            let path: Vec<&str> = posting.path.split(':').collect();
+            let account = accounts.add_path(&path);
+            accounts.get_node_at_index_mut(account).unwrap().value.push(index);
+
+            if let Some(amount) = &posting.amount {
+                if !amount.commodity.is_empty() {
+                    commodities.insert(amount.commodity.clone());
+                }
+            }
+        }
+        for price in &prices {
+            commodities.insert(price.commodity.clone());
+        }
+
+        Self {
+            transactions,
+            postings,
+            accounts,
+            commodities,
+            prices,
+            payees,
+            periodic_transactions: Vec::new(),
+            sources,
+            read_time: Duration::default(),
+        }
+    }
+
+    /// Adds the periodic transactions, which are kept apart from the transactions
+    pub fn with_periodic_transactions(mut self, periodic_transactions: Vec<PeriodicTransaction>) -> Self {
+        self.periodic_transactions = periodic_transactions;
+        self
+    }
+
+    /// Records how long reading the journal took
+    pub fn with_read_time(mut self, read_time: Duration) -> Self {
+        self.read_time = read_time;
+        self
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn postings(&self) -> &[Posting] {
+        &self.postings
+    }
+
+    /// The postings of `transaction`, which must be one of this journal's transactions
+    pub fn postings_for<'j>(&'j self, transaction: &'j Transaction) -> impl Iterator<Item = &'j Posting> {
+        transaction.postings.iter().map(move |index| &self.postings[*index])
+    }
+
+    /// The transaction `posting` belongs to, or None if postings were read without their transactions
+    pub fn transaction_of(&self, posting: &Posting) -> Option<&Transaction> {
+        posting.transaction.and_then(|index| self.transactions.get(index))
+    }
+
+    /// Every account posted to and their parents
+    pub fn accounts(&self) -> &Tree<Vec<usize>> {
+        &self.accounts
+    }
+
+    /// The commodities of every amount and price, in alphabetical order
+    pub fn commodities(&self) -> &BTreeSet<String> {
+        &self.commodities
+    }
+
+    /// The prices declared with price directives, in file order
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
+    }
+
+    /// The payees declared with payee directives, in file order
+    pub fn payees(&self) -> &[Payee] {
+        &self.payees
+    }
+
+    /// The transactions written with a `~` header, in file order
+    pub fn periodic_transactions(&self) -> &[PeriodicTransaction] {
+        &self.periodic_transactions
+    }
+
+    /// The paths of the files the journal was read from, including included files, and how many transactions
+    /// each contained
+    pub fn sources(&self) -> &HashMap<PathBuf, usize> {
+        &self.sources
+    }
+
+    /// How long reading the journal took, or zero if it wasn't recorded
+    pub fn read_time(&self) -> Duration {
+        self.read_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Amount;
+
+    fn journal() -> Journal {
+        let mut transaction = Transaction::new();
+        transaction.payee = "Shop".to_owned();
+        transaction.postings = vec![0, 1];
+
+        let posting = |path: &str, quantity| Posting {
+            path: path.to_owned(),
+            amount: Some(Amount::new(quantity, "£")),
+            comments: vec![],
+            transaction: Some(0),
+        };
+        let postings = vec![posting("Expenses:Food", 1000), posting("Assets:Current", -1000)];
+
+        let price = Price {
+            date: time::Date::try_from_ymd(2020, 1, 1).unwrap(),
+            commodity: "USD".to_owned(),
+            price: Amount::new(80, "£"),
+        };
+
+        Journal::new(vec![transaction], postings, HashMap::new(), vec![], vec![price])
+    }
+
+    #[test]
+    fn it_links_transactions_and_postings() {
+        let journal = journal();
+        let transaction = &journal.transactions()[0];
+
+        let accounts: Vec<&str> = journal.postings_for(transaction).map(|p| p.path.as_str()).collect();
+        assert_eq!(accounts, vec!["Expenses:Food", "Assets:Current"]);
+        assert_eq!(journal.transaction_of(&journal.postings()[1]).unwrap().payee, "Shop");
+    }
+
+    #[test]
+    fn it_builds_the_accounts_tree() {
+        let journal = journal();
+        let accounts = journal.accounts();
+
+        assert_eq!(accounts.get_node_at_path(&["Expenses", "Food"]).unwrap().value, vec![0]);
+        assert_eq!(
+            accounts.get_node_at_path(&["Assets", "Current"]).unwrap().value,
+            vec![1]
+        );
+        assert!(accounts.get_node_at_path(&["Assets"]).unwrap().value.is_empty());
+    }
+
+    #[test]
+    fn it_collects_commodities() {
+        let journal = journal();
+        let commodities: Vec<&str> = journal.commodities().iter().map(String::as_str).collect();
+        assert_eq!(commodities, vec!["USD", "£"]);
+    }
+}
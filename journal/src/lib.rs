@@ -1,13 +1,21 @@
 mod amount;
+mod journal;
+mod payee;
 mod periodic_transaction;
 mod posting;
+mod price;
 mod status;
+mod tag;
 mod transaction;
 
 pub use amount::Amount;
+pub use journal::Journal;
+pub use payee::Payee;
 pub use periodic_transaction::{Period, PeriodInterval, PeriodicTransaction};
 pub use posting::Posting;
+pub use price::Price;
 pub use status::Status;
+pub use tag::Tag;
 pub use transaction::Transaction;
 
 pub use time::Date;
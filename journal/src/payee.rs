@@ -0,0 +1,18 @@
+/// A payee declared with a `payee` directive
+///
+/// ```text
+/// payee Amazon
+///     alias AMZN Mktp.*
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Payee {
+    pub name: String,
+    /// Patterns matching payees which should be renamed to this one
+    pub aliases: Vec<String>,
+}
+
+impl Payee {
+    pub fn new(name: String) -> Self {
+        Self { name, aliases: vec![] }
+    }
+}
@@ -1,5 +1,4 @@
-use super::Transaction;
-use std::sync::Arc;
+use super::Posting;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodInterval {
@@ -11,32 +10,33 @@ pub enum PeriodInterval {
     Yearly,
 }
 
-#[derive(Default, Debug, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Period {
     // TODO: which of these should be optional?
     pub start_date: Option<time::Date>,
+    /// The first date after the period
     pub end_date: Option<time::Date>,
     pub interval: Option<PeriodInterval>,
     pub frequency: u8,
 }
 
-#[derive(Debug)]
-pub struct PeriodicTransaction {
-    period: Period,
-    transaction: Arc<Transaction>,
+impl Period {
+    /// Whether `date` is in the period, which is unbounded on any side without a date
+    pub fn includes(&self, date: time::Date) -> bool {
+        self.start_date.is_none_or(|start| start <= date) && self.end_date.is_none_or(|end| date < end)
+    }
 }
 
-impl PeriodicTransaction {
-    pub fn run(&self, start_date: time::Date) -> Vec<Arc<Transaction>> {
-        unimplemented!();
-        /*
-        match self.period.interval {
-            PeriodInterval::Budget => {
-                self.transaction.borrow_mut().date = start_date;
-                return vec![Arc::clone(&self.transaction)];
-            }
-            _ => return vec![],
-        }
-        */
-    }
+/// A transaction written with a `~` header, which recurs over its period instead of happening on a date
+///
+/// ```text
+/// ~ monthly from 2020-10
+///     Expenses:Groceries    £200
+/// ```
+///
+/// Its postings aren't part of the journal's postings and needn't balance; budgets use them as allocations.
+#[derive(Debug, Clone)]
+pub struct PeriodicTransaction {
+    pub period: Period,
+    pub postings: Vec<Posting>,
 }
@@ -1,11 +1,15 @@
 use super::amount::Amount;
+use super::Tag;
+use serde::Serialize;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Posting {
+    #[serde(rename = "account")]
     pub path: String,
     pub amount: Option<Amount>,
     pub comments: Vec<String>,
     // Index of the transaction
+    #[serde(skip)]
     pub transaction: Option<usize>,
 }
 
@@ -13,6 +17,29 @@ impl Posting {
     pub fn add_comment(&mut self, comment: String) {
         self.comments.push(comment)
     }
+
+    /// The tags in the posting's comments
+    pub fn tags(&self) -> Vec<Tag> {
+        self.comments.iter().flat_map(|comment| Tag::parse(comment)).collect()
+    }
+
+    /// Writes the posting in journal format with the account padded to `account_width`
+    pub fn write<W: std::fmt::Write>(&self, w: &mut W, account_width: usize, show_amount: bool) -> std::fmt::Result {
+        match &self.amount {
+            Some(amount) if show_amount => {
+                // Padded by display width so that wide and combining characters don't misalign the amounts
+                let padding = account_width.saturating_sub(tree::width(&self.path));
+                writeln!(w, "    {}{:padding$}  {}", self.path, "", amount, padding = padding)?
+            }
+            _ => writeln!(w, "    {}", self.path)?,
+        }
+
+        for comment in self.comments.iter() {
+            writeln!(w, "    ; {}", comment)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl PartialEq for Posting {
@@ -32,23 +59,27 @@ impl Eq for Posting {}
 
 impl std::fmt::Display for Posting {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut comments: Option<String> = None;
-        for comment in self.comments.iter() {
-            match comments {
-                Some(c) => comments = Some(format!("{}\n    ; {}", c, comment)),
-                None => comments = Some(format!("    ; {}", comment)),
-            }
-        }
+        self.write(f, 0, true)
+    }
+}
 
-        match &self.amount {
-            Some(a) => match comments {
-                Some(c) => return write!(f, "  {}\t{}\n{}\n", self.path, a, c),
-                None => return write!(f, "  {}\t{}\n", self.path, a),
-            },
-            None => match comments {
-                Some(c) => return write!(f, "  {}\n{}\n", self.path, c),
-                None => return write!(f, "  {}\n", self.path),
-            },
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_pads_accounts_by_display_width() {
+        let posting = |path: &str, quantity| Posting {
+            path: path.to_owned(),
+            amount: Some(Amount::new(quantity, "£")),
+            ..Posting::default()
+        };
+
+        let mut output = String::new();
+        posting("Expenses:日本", 1000).write(&mut output, 14, true).unwrap();
+        posting("Expenses:Cafe\u{301}", 500)
+            .write(&mut output, 14, true)
+            .unwrap();
+        assert_eq!(output, "    Expenses:日本   £10.00\n    Expenses:Cafe\u{301}   £5.00\n");
     }
 }
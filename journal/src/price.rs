@@ -0,0 +1,14 @@
+use super::Amount;
+
+/// The price of a commodity from a date, declared with a price directive
+///
+/// ```text
+/// P 2020-01-01 USD £0.80
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Price {
+    pub date: time::Date,
+    pub commodity: String,
+    /// The value of one unit of the commodity
+    pub price: Amount,
+}
@@ -1,18 +1,18 @@
-#[derive(Debug, Clone, Eq, PartialEq)]
+use serde::Serialize;
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize)]
 // TODO rename TransactionStatus ?
 pub enum Status {
     // TODO: change to None
+    #[default]
+    #[serde(rename = "none")]
     NoStatus,
+    #[serde(rename = "cleared")]
     Cleared,
+    #[serde(rename = "pending")]
     Uncleared,
 }
 
-impl Default for Status {
-    fn default() -> Self {
-        Status::NoStatus
-    }
-}
-
 impl std::fmt::Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
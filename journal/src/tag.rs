@@ -0,0 +1,68 @@
+/// A tag found in a comment, either `:name:` or `name: value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Tag {
+    /// Finds the tags in a comment, in the order they appear
+    ///
+    /// Tags are written as `:one:two:` or as `name: value`, where the value is the rest of the comment
+    pub fn parse(comment: &str) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        let mut rest = comment.trim_start();
+
+        while !rest.is_empty() {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (word, remaining) = rest.split_at(end);
+
+            if word.len() > 1 && word.starts_with(':') && word.ends_with(':') {
+                tags.extend(word.split(':').filter(|name| !name.is_empty()).map(|name| Tag {
+                    name: name.to_owned(),
+                    value: None,
+                }));
+            } else if let Some(name) = word.strip_suffix(':') {
+                if !name.is_empty() && !name.contains(':') {
+                    let value = remaining.trim();
+                    tags.push(Tag {
+                        name: name.to_owned(),
+                        value: if value.is_empty() { None } else { Some(value.to_owned()) },
+                    });
+                    break;
+                }
+            }
+
+            rest = remaining.trim_start();
+        }
+
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, value: Option<&str>) -> Tag {
+        Tag {
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn it_parses_tags() {
+        assert_eq!(Tag::parse("new shoes"), vec![]);
+        assert_eq!(Tag::parse(":holiday:"), vec![tag("holiday", None)]);
+        assert_eq!(
+            Tag::parse("trip :holiday:france: booked"),
+            vec![tag("holiday", None), tag("france", None)]
+        );
+        assert_eq!(
+            Tag::parse(":shared: receipt: in the drawer"),
+            vec![tag("shared", None), tag("receipt", Some("in the drawer"))]
+        );
+        assert_eq!(Tag::parse("see https://example.com"), vec![]);
+    }
+}
@@ -1,25 +1,38 @@
 use super::Status;
+use serde::{Serialize, Serializer};
 use std::cmp::Ordering;
 
 use super::Posting;
+use super::Tag;
 
-#[derive(Debug, PartialEq, Eq)]
+/// Serialises without its postings because they are only indexes; reports serialise them alongside
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct Transaction {
+    #[serde(serialize_with = "serialize_date")]
     pub date: time::Date,
     // TODO: This should be optional
     pub payee: String,
     pub status: Status,
     pub header_comment: Option<String>,
     /// Indexes of the postings vec
+    #[serde(skip)]
     pub postings: Vec<usize>,
     pub comments: Vec<String>,
+    /// Index into `postings` of the posting whose amount was elided and inferred while reading
+    #[serde(skip)]
     pub elided_amount_posting_index: Option<usize>,
 }
 
+impl Default for Transaction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Transaction {
     pub fn new() -> Self {
         Self {
-            date: time::Date::try_from_ymd(2020, 01, 01).unwrap(),
+            date: time::Date::try_from_ymd(2020, 1, 1).unwrap(),
             status: Status::NoStatus,
             payee: String::from(""),
             header_comment: None,
@@ -29,34 +42,56 @@ impl Transaction {
         }
     }
 
-    pub fn display(&self, postings: &[Posting]) {
-        let mut comments: Option<String> = None;
-        for comment in self.comments.iter() {
-            match comments {
-                Some(c) => comments = Some(format!("{}\n  ; {}", c, comment)),
-                None => comments = Some(format!("  ; {}", comment)),
-            }
+    /// The tags in the transaction's header comment and comments, not including its postings
+    pub fn tags(&self) -> Vec<Tag> {
+        self.header_comment
+            .iter()
+            .chain(self.comments.iter())
+            .flat_map(|comment| Tag::parse(comment))
+            .collect()
+    }
+
+    /// Writes the transaction in journal format
+    /// If `explicit` is set, amounts which were elided in the journal are written too
+    pub fn write<W: std::fmt::Write>(&self, w: &mut W, postings: &[Posting], explicit: bool) -> std::fmt::Result {
+        write!(w, "{}", self.date)?;
+        if self.status != Status::NoStatus {
+            write!(w, " {}", self.status)?;
+        }
+        if !self.payee.is_empty() {
+            write!(w, " {}", self.payee)?;
         }
+        if let Some(comment) = &self.header_comment {
+            write!(w, "  ; {}", comment)?;
+        }
+        writeln!(w)?;
 
-        let mut posting_output = String::new();
-        for p_idx in self.postings.iter() {
-            posting_output.push_str(&postings[*p_idx].to_string())
+        for comment in self.comments.iter() {
+            writeln!(w, "    ; {}", comment)?;
         }
 
-        match comments {
-            Some(c) => {
-                println!(
-                    "{} {} {}\n{}\n{}",
-                    self.date, self.status, self.payee, c, posting_output,
-                )
-            }
-            None => {
-                println!("{} {} {}\n{}", self.date, self.status, self.payee, posting_output,)
-            }
+        // Align the amounts of all the postings
+        let account_width = self
+            .postings
+            .iter()
+            .map(|p_idx| tree::width(&postings[*p_idx].path))
+            .max()
+            .unwrap_or(0);
+
+        for (i, p_idx) in self.postings.iter().enumerate() {
+            let show_amount = explicit || self.elided_amount_posting_index != Some(i);
+            postings[*p_idx].write(w, account_width, show_amount)?;
         }
+
+        Ok(())
     }
 }
 
+/// Dates are written as YYYY-MM-DD
+fn serialize_date<S: Serializer>(date: &time::Date, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
 impl Ord for Transaction {
     fn cmp(&self, other: &Self) -> Ordering {
         self.date.cmp(&other.date)
@@ -71,14 +106,11 @@ impl PartialOrd for Transaction {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::amount::Amount;
-
     #[test]
     fn it_works() {
         /*
         let th = TransactionHeader {
-            date: time::Date::try_from_ymd(2020, 01, 01).unwrap(),
+            date: time::Date::try_from_ymd(2020, 1, 1).unwrap(),
             payee: "A Shop".to_owned(),
             status: Status::Cleared,
             comment: None,
@@ -0,0 +1,68 @@
+use std::{io, path::Path};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use reader::reader::{Config, Reader};
+use reader::stream::Stream;
+
+/// A journal of `count` transactions with a mix of statuses, comments and commodities
+fn journal(count: usize) -> String {
+    let mut journal = String::from("; A generated journal\n\npayee Grocer\n    alias ^supermarket\n\n");
+    for i in 0..count {
+        let status = ["* ", "! ", ""][i % 3];
+        let commodity = ["£", "$"][i % 2];
+        journal.push_str(&format!(
+            "2020-{:02}-{:02} {}Supermarket {}  ; :groceries:\n    ; receipt {}\n    Expenses:Food:Category {}    {}{}.{:02}  ; item: {}\n    Assets:Current\n\n",
+            i % 12 + 1,
+            i % 28 + 1,
+            status,
+            i % 50,
+            i,
+            i % 20,
+            commodity,
+            i % 1000,
+            i % 100,
+            i % 7,
+        ));
+    }
+    journal
+}
+
+fn read_str(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_str");
+    for count in &[100, 10_000] {
+        let journal = journal(*count);
+        group.throughput(Throughput::Bytes(journal.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &journal, |b, journal| {
+            b.iter(|| Reader::new().read_str(black_box(journal), Config::new()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Streams read a line at a time, where `Reader` reads each file whole
+fn stream(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stream");
+    for count in &[100, 10_000] {
+        let journal = journal(*count);
+        group.throughput(Throughput::Bytes(journal.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &journal, |b, journal| {
+            b.iter(|| {
+                let contents = io::Cursor::new(black_box(journal).clone());
+                Stream::from_reader("<string>", contents, false).for_each(|entry| {
+                    entry.unwrap();
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn read_files(c: &mut Criterion) {
+    let location = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/test.journal");
+    c.bench_function("read test.journal", |b| {
+        b.iter(|| Reader::new().read(black_box(&location), Config::new()).unwrap())
+    });
+}
+
+criterion_group!(benches, read_str, stream, read_files);
+criterion_main!(benches);
@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::{ops::Range, path::PathBuf};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 /// Type of line found in a journal file
 #[derive(Debug)]
@@ -7,8 +9,10 @@ pub enum LineType {
     Comment,
     IncludeDirective,
     TransactionHeader,
-    PeriodidTransactionHeader,
+    PeriodicTransactionHeader,
     Posting,
+    PayeeDirective,
+    PriceDirective,
 }
 
 impl std::fmt::Display for LineType {
@@ -18,12 +22,17 @@ impl std::fmt::Display for LineType {
             LineType::Comment => write!(f, "comment"),
             LineType::IncludeDirective => write!(f, "include directive"),
             LineType::TransactionHeader => write!(f, "transaction header"),
-            LineType::PeriodidTransactionHeader => write!(f, "periodic transaction header"),
+            LineType::PeriodicTransactionHeader => write!(f, "periodic transaction header"),
             LineType::Posting => write!(f, "posting"),
+            LineType::PayeeDirective => write!(f, "payee directive"),
+            LineType::PriceDirective => write!(f, "price directive"),
         }
     }
 }
 
+/// How many columns a tab is expanded to when rendering
+const TAB_WIDTH: usize = 4;
+
 /// Packages an ErrorKind with location information
 #[derive(Debug)]
 pub struct Error {
@@ -31,8 +40,93 @@ pub struct Error {
     pub kind: ErrorKind,
     /// The path of the file where the error was detected
     pub location: PathBuf,
-    /// One-based line number where the error was detected
+    /// One-based line number where the error was detected (zero if the error isn't tied to a line)
     pub line: u64,
+    /// Zero-based range of characters on the line where the error was detected
+    /// If this is None, the error applies to the whole line
+    pub columns: Option<Range<usize>>,
+    /// The text of the line where the error was detected
+    pub text: Option<String>,
+}
+
+impl Error {
+    pub fn new<P: Into<PathBuf>>(kind: ErrorKind, location: P, line: u64) -> Self {
+        Self {
+            kind,
+            location: location.into(),
+            line,
+            columns: None,
+            text: None,
+        }
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Range<usize>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// The columns to highlight, falling back to the line without indentation or trailing space
+    fn span(&self) -> Option<Range<usize>> {
+        if let Some(columns) = &self.columns {
+            return Some(columns.clone());
+        }
+
+        let text = self.text.as_ref()?;
+        let start = text.chars().take_while(|c| c.is_whitespace()).count();
+        let end = text.trim_end().chars().count();
+        Some(start..end.max(start))
+    }
+
+    /// Renders the error with the offending line and a caret under the problem, in the style of rustc:
+    ///
+    /// ```text
+    /// error: failed to parse posting
+    ///   --> tests/errors.journal:14:23
+    ///    |
+    /// 14 |     Assets:Current    £-2O.00
+    ///    |                       ^^^^^^^
+    /// ```
+    pub fn render(&self) -> String {
+        let mut output = format!("error: {}\n", self.kind);
+
+        let span = self.span();
+        let gutter = " ".repeat(self.line.to_string().len());
+
+        match (self.line, &span) {
+            (0, _) => output.push_str(&format!("{}--> {}\n", gutter, self.location.display())),
+            (line, None) => output.push_str(&format!("{}--> {}:{}\n", gutter, self.location.display(), line)),
+            (line, Some(span)) => output.push_str(&format!(
+                "{}--> {}:{}:{}\n",
+                gutter,
+                self.location.display(),
+                line,
+                span.start + 1
+            )),
+        }
+
+        if let (Some(text), Some(span)) = (&self.text, &span) {
+            // Tabs are expanded so that the caret lines up with the text
+            let width_of = |c: char| if c == '\t' { TAB_WIDTH } else { 1 };
+            let offset: usize = text.chars().take(span.start).map(width_of).sum();
+            let width: usize = text.chars().take(span.end).skip(span.start).map(width_of).sum();
+            let width = width.max(1);
+
+            output.push_str(&format!("{} |\n", gutter));
+            output.push_str(&format!(
+                "{} | {}\n",
+                self.line,
+                text.trim_end().replace('\t', &" ".repeat(TAB_WIDTH))
+            ));
+            output.push_str(&format!("{} | {}{}\n", gutter, " ".repeat(offset), "^".repeat(width)));
+        }
+
+        output
+    }
 }
 
 /// Indicates an error during reading of a journal file
@@ -40,6 +134,9 @@ pub struct Error {
 pub enum ErrorKind {
     IncorrectFormatting(String),
     DuplicateSource(PathBuf),
+    IncludeNotFound(PathBuf),
+    InvalidInclude(String),
+    InvalidAlias(String),
     UnexpectedItem(LineType),
     MissingPosting,
     MissingTransaction,
@@ -51,14 +148,41 @@ pub enum ErrorKind {
     Parse(LineType),
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error {
-            kind: ErrorKind::IO(err),
-            // TODO this is not correct
-            location: PathBuf::new(),
-            // TODO this is not correct
-            line: 0,
+impl ErrorKind {
+    /// A stable identifier for the kind of error, used in machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::IncorrectFormatting(_) => "incorrect-formatting",
+            ErrorKind::DuplicateSource(_) => "duplicate-source",
+            ErrorKind::IncludeNotFound(_) => "include-not-found",
+            ErrorKind::InvalidInclude(_) => "invalid-include",
+            ErrorKind::InvalidAlias(_) => "invalid-alias",
+            ErrorKind::UnexpectedItem(_) => "unexpected-item",
+            ErrorKind::MissingPosting => "missing-posting",
+            ErrorKind::MissingTransaction => "missing-transaction",
+            ErrorKind::TwoPostingsWithElidedAmounts => "two-elided-amounts",
+            ErrorKind::TransactionDoesNotBalance => "unbalanced-transaction",
+            ErrorKind::IO(_) => "io",
+            ErrorKind::Parse(_) => "parse",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedItem(item) => write!(f, "unexpected {}", item),
+            ErrorKind::MissingPosting => write!(f, "missing posting"),
+            ErrorKind::MissingTransaction => write!(f, "missing transaction"),
+            ErrorKind::TwoPostingsWithElidedAmounts => write!(f, "two postings with elided amounts"),
+            ErrorKind::TransactionDoesNotBalance => write!(f, "transaction does not balance"),
+            ErrorKind::IO(e) => write!(f, "{}", e),
+            ErrorKind::Parse(item) => write!(f, "failed to parse {}", item),
+            ErrorKind::DuplicateSource(path) => write!(f, "{} has already been read", path.display()),
+            ErrorKind::IncludeNotFound(path) => write!(f, "could not find included file {}", path.display()),
+            ErrorKind::InvalidInclude(desc) => write!(f, "invalid include: {}", desc),
+            ErrorKind::InvalidAlias(desc) => write!(f, "invalid alias: {}", desc),
+            ErrorKind::IncorrectFormatting(desc) => write!(f, "incorrect formatting: {}", desc),
         }
     }
 }
@@ -67,38 +191,82 @@ impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.kind {
-            ErrorKind::UnexpectedItem(item) => {
-                write!(f, "Unexpected {} on line {}", item, self.line)
-            }
-            ErrorKind::MissingPosting => {
-                write!(f, "Missing posting on line {}", self.line)
-            }
-            ErrorKind::MissingTransaction => {
-                write!(f, "Missing transaction on line {}", self.line)
-            }
-            ErrorKind::TwoPostingsWithElidedAmounts => {
-                write!(f, "Two postings with elided amounts on line {}", self.line)
-            }
-            ErrorKind::TransactionDoesNotBalance => {
-                write!(
-                    f,
-                    "{:?}: Transaction ending on line {} does not balance",
-                    self.location, self.line
-                )
-            }
-            ErrorKind::IO(e) => {
-                write!(f, "An IO error occurred on line {}: {:?}", self.line, e)
-            }
-            ErrorKind::Parse(item) => {
-                write!(f, "Failed to parse {} at {:?}:{}", item, self.location, self.line)
-            }
-            ErrorKind::DuplicateSource(path) => {
-                write!(f, "Found cyclic import of {:?}", path.as_path())
-            }
-            ErrorKind::IncorrectFormatting(desc) => {
-                write!(f, "Incorrect formatting on line {}: {}", self.line, desc)
-            }
+        match (self.line, self.span()) {
+            (0, _) => write!(f, "{}: {}", self.location.display(), self.kind),
+            (line, None) => write!(f, "{}:{}: {}", self.location.display(), line, self.kind),
+            (line, Some(span)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.location.display(),
+                line,
+                span.start + 1,
+                self.kind
+            ),
         }
     }
 }
+
+/// Serialises to an object with the schema:
+///
+/// ```text
+/// {
+///   "file": string,
+///   "line": number,               // one-based, zero if not tied to a line
+///   "column_start": number|null,  // one-based
+///   "column_end": number|null,    // one-based, exclusive
+///   "code": string,               // see ErrorKind::code
+///   "message": string,
+///   "text": string|null           // the offending line
+/// }
+/// ```
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let span = self.span();
+
+        let mut state = serializer.serialize_struct("Error", 7)?;
+        state.serialize_field("file", &self.location)?;
+        state.serialize_field("line", &self.line)?;
+        state.serialize_field("column_start", &span.as_ref().map(|s| s.start + 1))?;
+        state.serialize_field("column_end", &span.as_ref().map(|s| s.end + 1))?;
+        state.serialize_field("code", self.kind.code())?;
+        state.serialize_field("message", &self.kind.to_string())?;
+        state.serialize_field("text", &self.text)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn posting_error() -> Error {
+        Error::new(ErrorKind::Parse(LineType::Posting), "a.journal", 14)
+            .with_text("\tAssets:Current    £-2O.00 ")
+            .with_columns(19..26)
+    }
+
+    #[test]
+    fn it_displays_the_location() {
+        assert_eq!(posting_error().to_string(), "a.journal:14:20: failed to parse posting");
+
+        let error = Error::new(ErrorKind::MissingPosting, "a.journal", 0);
+        assert_eq!(error.to_string(), "a.journal: missing posting");
+    }
+
+    #[test]
+    fn it_renders_a_caret_under_the_problem() {
+        let expected = "error: failed to parse posting
+  --> a.journal:14:20
+   |
+14 |     Assets:Current    £-2O.00
+   |                       ^^^^^^^
+";
+        assert_eq!(posting_error().render(), expected);
+    }
+
+    #[test]
+    fn it_highlights_the_whole_line_without_columns() {
+        let error = Error::new(ErrorKind::TransactionDoesNotBalance, "a.journal", 3).with_text("2020-01-01 Shop  ");
+        assert_eq!(error.span(), Some(0..15));
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use super::error::ErrorKind;
+
+/// Resolves the argument of an include directive to the files it refers to.
+///
+/// Relative paths are resolved against `parent`, a leading `~` is expanded to the home directory and
+/// glob patterns are expanded to every matching file (in alphabetical order). `current` is the file
+/// containing the directive and is never returned so that `include *.journal` doesn't include itself.
+pub fn resolve(include: &str, parent: &Path, current: &Path) -> Result<Vec<PathBuf>, ErrorKind> {
+    let include = include.trim();
+    if include.is_empty() {
+        return Err(ErrorKind::InvalidInclude("missing path".to_string()));
+    }
+
+    let path = parent.join(expand_home(include)?);
+
+    if !is_glob(include) {
+        if !path.is_file() {
+            return Err(ErrorKind::IncludeNotFound(path));
+        }
+        return Ok(vec![path]);
+    }
+
+    let pattern = path.to_string_lossy();
+    let entries = glob::glob(&pattern).map_err(|e| ErrorKind::InvalidInclude(e.to_string()))?;
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| ErrorKind::IO(e.into()))?;
+        if entry.is_file() && entry != current {
+            paths.push(entry);
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(ErrorKind::IncludeNotFound(path));
+    }
+
+    Ok(paths)
+}
+
+/// Replaces a leading `~` with the user's home directory
+fn expand_home(include: &str) -> Result<PathBuf, ErrorKind> {
+    let rest = match include.strip_prefix('~') {
+        None => return Ok(PathBuf::from(include)),
+        Some(rest) => rest,
+    };
+
+    // We don't support the ~user form
+    if !rest.is_empty() && !rest.starts_with(std::path::is_separator) {
+        return Err(ErrorKind::InvalidInclude(format!("cannot expand {}", include)));
+    }
+
+    match dirs::home_dir() {
+        None => Err(ErrorKind::InvalidInclude("could not find home directory".to_string())),
+        Some(home) => Ok(home.join(rest.trim_start_matches(std::path::is_separator))),
+    }
+}
+
+fn is_glob(include: &str) -> bool {
+    include.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tests_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests")
+    }
+
+    #[test]
+    fn it_resolves_relative_paths() {
+        let dir = tests_dir();
+        let paths = resolve("included.journal", &dir, &dir.join("test.journal")).unwrap();
+        assert_eq!(paths, vec![dir.join("included.journal")]);
+    }
+
+    #[test]
+    fn it_resolves_absolute_paths() {
+        let dir = tests_dir();
+        let absolute = dir.join("included.journal");
+        let paths = resolve(
+            absolute.to_str().unwrap(),
+            Path::new("/elsewhere"),
+            &dir.join("test.journal"),
+        )
+        .unwrap();
+        assert_eq!(paths, vec![absolute]);
+    }
+
+    #[test]
+    fn it_expands_globs_without_including_itself() {
+        let dir = tests_dir();
+        let current = dir.join("test.journal");
+        let paths = resolve("*test.journal", &dir, &current).unwrap();
+        assert_eq!(
+            paths,
+            vec![dir.join("age-test.journal"), dir.join("budget-test.journal")]
+        );
+    }
+
+    #[test]
+    fn it_expands_the_home_directory() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~/a.journal").unwrap(), home.join("a.journal"));
+        assert_eq!(expand_home("~").unwrap(), home);
+        assert!(expand_home("~someone/a.journal").is_err());
+    }
+
+    #[test]
+    fn it_reports_missing_files() {
+        let dir = tests_dir();
+        let current = dir.join("test.journal");
+
+        match resolve("missing.journal", &dir, &current) {
+            Err(ErrorKind::IncludeNotFound(path)) => assert_eq!(path, dir.join("missing.journal")),
+            _ => panic!("expected a missing include"),
+        }
+
+        match resolve("missing/*.journal", &dir, &current) {
+            Err(ErrorKind::IncludeNotFound(_)) => {}
+            _ => panic!("expected a missing include"),
+        }
+    }
+}
@@ -1,12 +1,13 @@
 extern crate rayon;
 extern crate time;
 
-mod bufreader;
 // TODO reexport from here to flatten the heirarchy
 pub mod error;
+mod include;
 // TODO reexport from here to flatten the heirarchy
 pub mod reader;
 mod source;
-mod transaction_header;
+pub mod stream;
+pub mod syntax;
 
 pub use time::Date;
@@ -1,21 +1,42 @@
-use super::{error::Error, error::ErrorKind, source::ItemKind, source::Source};
+use super::{error::Error, source::Reading, stream::Stream};
 
+use journal::Journal;
+use journal::Payee;
+use journal::PeriodicTransaction;
 use journal::Posting;
+use journal::Price;
 use journal::Transaction;
 
-use std::sync::mpsc;
-use std::thread;
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read},
+    path::PathBuf,
+    time::Instant,
+};
 
 use rayon::prelude::*;
 
 const TRANSACTION_COUNT: usize = 1024;
 const POSTING_COUNT: usize = TRANSACTION_COUNT * 2;
 
+/// Passing this as the location to `Reader::read` reads the journal from stdin
+pub const STDIN: &str = "-";
+
+/// The transactions, postings and paths of the files they were read from
+pub type ReadResult = Result<(Vec<Transaction>, Vec<Posting>, HashSet<PathBuf>), Error>;
+
 pub struct Config {
     pub should_sort: bool,
     pub read_postings: bool,
     pub read_transactions: bool,
+    /// Carry on after errors, collecting them rather than returning the first
+    pub recover: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
@@ -24,81 +45,182 @@ impl Config {
             should_sort: false,
             read_postings: true,
             read_transactions: true,
+            recover: false,
         }
     }
 }
 
 /// Reader reads ledger files and returns the transactions, postings and included file paths it found
-pub struct Reader {}
+pub struct Reader {
+    errors: Vec<Error>,
+    payees: Vec<Payee>,
+    prices: Vec<Price>,
+    periodic_transactions: Vec<PeriodicTransaction>,
+    transaction_counts: HashMap<PathBuf, usize>,
+}
+
+impl Default for Reader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Reader {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            errors: Vec::new(),
+            payees: Vec::new(),
+            prices: Vec::new(),
+            periodic_transactions: Vec::new(),
+            transaction_counts: HashMap::new(),
+        }
+    }
+
+    /// The errors found by the last read when `Config::recover` was set
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// The payees declared with payee directives in the last read
+    pub fn payees(&self) -> &[Payee] {
+        &self.payees
+    }
+
+    /// The prices declared with price directives in the last read
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
     }
 
-    pub fn read<P: Into<PathBuf>>(
-        &mut self,
-        location: P,
-        config: Config,
-    ) -> Result<(Vec<Transaction>, Vec<Posting>, HashSet<PathBuf>), Error> {
-        let location = location.into();
-        let (send, recv) = mpsc::channel();
+    /// The transactions written with a `~` header in the last read, which aren't among the transactions read
+    pub fn periodic_transactions(&self) -> &[PeriodicTransaction] {
+        &self.periodic_transactions
+    }
 
-        let source_location = location.clone();
-        thread::spawn(move || {
-            let mut source = Source::new(source_location);
-            source.parse(send);
-        });
+    /// How many transactions the last read found in each source, leaving out sources with none
+    pub fn transaction_counts(&self) -> &HashMap<PathBuf, usize> {
+        &self.transaction_counts
+    }
+
+    /// Reads the journal at `location`, or stdin if it is `STDIN`
+    pub fn read<P: Into<PathBuf>>(&mut self, location: P, config: Config) -> ReadResult {
+        self.read_files(std::iter::once(location), config)
+    }
+
+    /// Reads the journals at `locations` and merges them in the order given
+    /// Any of the locations may be `STDIN`
+    /// Everything read is kept, so each file is read whole rather than a line at a time, which is faster
+    pub fn read_files<I, P>(&mut self, locations: I, config: Config) -> ReadResult
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.read_stream(Stream::open_with(locations, config.recover, Reading::Whole), config)
+    }
+
+    /// Reads the journals at `locations` like `read_files`, collecting everything read into a `Journal`
+    pub fn read_journal<I, P>(&mut self, locations: I, config: Config) -> Result<Journal, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        let start = Instant::now();
+        let (transactions, postings, sources) = self.read_files(locations, config)?;
+        let read_time = start.elapsed();
+
+        let sources = sources
+            .into_iter()
+            .map(|source| {
+                let count = self.transaction_counts.get(&source).copied().unwrap_or(0);
+                (source, count)
+            })
+            .collect();
+        let journal = Journal::new(
+            transactions,
+            postings,
+            sources,
+            self.payees.clone(),
+            self.prices.clone(),
+        );
+
+        Ok(journal
+            .with_periodic_transactions(self.periodic_transactions.clone())
+            .with_read_time(read_time))
+    }
+
+    /// Reads a journal held in memory
+    /// Included files are resolved relative to the current directory
+    pub fn read_str(&mut self, contents: &str, config: Config) -> ReadResult {
+        self.read_from("<string>", io::Cursor::new(contents.to_owned()), config)
+    }
+
+    /// Reads a journal from `contents`, naming it `location` in errors
+    /// Included files are resolved relative to the current directory
+    pub fn read_from<P, R>(&mut self, location: P, contents: R, config: Config) -> ReadResult
+    where
+        P: Into<PathBuf>,
+        R: Read + Send + 'static,
+    {
+        let stream = Stream::from_reader_with(location, contents, config.recover, Reading::Whole);
+        self.read_stream(stream, config)
+    }
+
+    /// Collects everything in the stream
+    fn read_stream(&mut self, stream: Stream, config: Config) -> ReadResult {
+        self.errors.clear();
 
         // The Reader caches the txs and postings so that consumers don't have to worry
         // about errors occuring mid-stream.
         let mut transactions = Vec::with_capacity(if config.read_transactions { TRANSACTION_COUNT } else { 0 });
         let mut postings = Vec::with_capacity(if config.read_postings { POSTING_COUNT } else { 0 });
-        let mut visited_sources = HashSet::new(); // HashSet<Arc<PathBuf>>,
-        visited_sources.insert(location.into());
 
-        for t in recv {
-            match t {
+        // Every alias is applied once everything is read, below
+        let mut stream = stream.without_renaming();
+        for entry in &mut stream {
+            let (mut t, mut p) = match entry {
+                Ok(entry) => entry,
+                Err(e) if config.recover => {
+                    self.errors.push(e);
+                    continue;
+                }
                 Err(e) => return Err(e),
-                Ok(r) => match r.kind {
-                    ItemKind::Transaction(mut t, mut p) => {
-                        // Link the postings and txs by idx
-                        let t_index = transactions.len();
-                        let p_indices = (postings.len()..postings.len() + p.len()).collect();
-
-                        if config.read_transactions {
-                            if config.read_postings {
-                                t.postings = p_indices;
-                            }
-                            transactions.push(t);
-                        }
-
-                        if config.read_postings {
-                            if config.read_transactions && !config.should_sort {
-                                for p in &mut p {
-                                    p.transaction = Some(t_index);
-                                }
-                            }
-                            postings.append(&mut p);
-                        }
-                    }
-                    ItemKind::IncludeDirective(include) => {
-                        if !visited_sources.insert(include.clone()) {
-                            let error = Error {
-                                kind: ErrorKind::DuplicateSource(include),
-                                line: 0,
-                                location: r.location,
-                            };
-                            return Err(error);
-                        }
+            };
+
+            // Link the postings and txs by idx
+            let t_index = transactions.len();
+            let p_indices = (postings.len()..postings.len() + p.len()).collect();
+
+            if config.read_transactions {
+                if config.read_postings {
+                    t.postings = p_indices;
+                }
+                transactions.push(t);
+            }
+
+            if config.read_postings {
+                if config.read_transactions && !config.should_sort {
+                    for p in &mut p {
+                        p.transaction = Some(t_index);
                     }
-                    _ => {}
-                },
+                }
+                postings.append(&mut p);
+            }
+        }
+
+        self.payees = stream.payees().to_vec();
+        self.prices = stream.prices().to_vec();
+        self.periodic_transactions = stream.periodic_transactions().to_vec();
+        self.transaction_counts = stream.transaction_counts().clone();
+
+        // Directives can come after the transactions they apply to, so aliases are applied once everything is read
+        for transaction in &mut transactions {
+            if let Some(name) = stream.alias_of(&transaction.payee) {
+                transaction.payee = name.to_owned();
             }
         }
 
         if config.should_sort {
-            &transactions.par_sort_unstable();
+            // A stable sort keeps transactions on the same date in file order
+            transactions.par_sort();
             for (t_idx, t) in transactions.iter().enumerate() {
                 for p_idx in t.postings.iter() {
                     postings[*p_idx].transaction = Some(t_idx)
@@ -106,6 +228,412 @@ impl Reader {
             }
         }
 
-        return Ok((transactions, postings, visited_sources));
+        Ok((transactions, postings, stream.sources().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorKind, LineType};
+    use journal::{Amount, Period};
+    use std::path::Path;
+
+    fn journal(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests").join(name)
+    }
+
+    fn read_payees(should_sort: bool) -> Vec<String> {
+        let location = journal("test.journal");
+        let mut config = Config::new();
+        config.should_sort = should_sort;
+
+        let (transactions, _, _) = Reader::new().read(location, config).unwrap();
+        transactions.into_iter().map(|t| t.payee).collect()
+    }
+
+    #[test]
+    fn it_reads_included_transactions_in_file_order() {
+        let expected = vec![
+            "A restaurant",
+            "Clothes store",
+            "Jéby̆",
+            "Grocery store",
+            "A restaurant",
+            "clothes store",
+            "Cash",
+        ];
+
+        // Included files are parsed on other threads so make sure scheduling can't change the order
+        for _ in 0..10 {
+            assert_eq!(read_payees(false), expected);
+        }
+    }
+
+    #[test]
+    fn it_sorts_transactions_by_date() {
+        let expected = vec![
+            "Jéby̆",
+            "Grocery store",
+            "A restaurant",
+            "Cash",
+            "A restaurant",
+            "Clothes store",
+            "clothes store",
+        ];
+        assert_eq!(read_payees(true), expected);
+    }
+
+    #[test]
+    fn it_stops_at_the_first_error() {
+        let mut reader = Reader::new();
+        match reader.read(journal("errors.journal"), Config::new()) {
+            Err(e) => assert_eq!(e.line, 8),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn it_collects_every_error_when_recovering() {
+        let mut reader = Reader::new();
+        let mut config = Config::new();
+        config.recover = true;
+
+        let (transactions, _, _) = reader.read(journal("errors.journal"), config).unwrap();
+        let payees: Vec<&str> = transactions.iter().map(|t| t.payee.as_str()).collect();
+        assert_eq!(payees, vec!["Salary", "Cash", "Book shop"]);
+
+        let lines: Vec<u64> = reader.errors().iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![8, 14, 23, 28, 32]);
+    }
+
+    #[test]
+    fn it_keeps_transactions_before_bad_headers_and_directives() {
+        for bad in &["2020-13-01 Bad", "payee", "P 2020-01-01 USD", "~ weekly"] {
+            let journal = format!(
+                "2020-01-01 Shop
+    Expenses:Food    £10
+    Assets:Current
+{}
+    Expenses:Food    £5
+    Assets:Current
+
+2020-01-03 Cafe
+    Expenses:Food    £3
+    Assets:Current
+",
+                bad
+            );
+            let mut reader = Reader::new();
+            let mut config = Config::new();
+            config.recover = true;
+
+            let (transactions, _, _) = reader.read_str(&journal, config).unwrap();
+            let payees: Vec<&str> = transactions.iter().map(|t| t.payee.as_str()).collect();
+            assert_eq!(payees, vec!["Shop", "Cafe"], "after {:?}", bad);
+
+            let lines: Vec<u64> = reader.errors().iter().map(|e| e.line).collect();
+            assert_eq!(lines, vec![4], "after {:?}", bad);
+        }
+    }
+
+    #[test]
+    fn it_reports_invalid_utf8_on_its_line() {
+        let contents = b"2020-01-01 Shop\n    Expenses:Food    \xa310\n    Assets:Current\n".to_vec();
+        let error = Reader::new()
+            .read_from("<bytes>", io::Cursor::new(contents), Config::new())
+            .unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::IO(_)));
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn it_reads_strings() {
+        let journal = "2020-01-01 * Shop  ; weekly
+    ; a note
+    Expenses:Food    £10.50
+    Assets:Current
+";
+        let (transactions, postings, sources) = Reader::new().read_str(journal, Config::new()).unwrap();
+
+        assert_eq!(transactions.len(), 1);
+        let transaction = &transactions[0];
+        assert_eq!(transaction.payee, "Shop");
+        assert_eq!(transaction.status, journal::Status::Cleared);
+        assert_eq!(transaction.header_comment.as_deref(), Some("weekly"));
+        assert_eq!(transaction.comments, vec!["a note"]);
+        assert_eq!(transaction.postings, vec![0, 1]);
+
+        let quantities: Vec<i64> = postings.iter().map(|p| p.amount.as_ref().unwrap().quantity).collect();
+        assert_eq!(quantities, vec![1050, -1050]);
+        assert!(postings.iter().all(|p| p.transaction == Some(0)));
+        assert_eq!(transaction.elided_amount_posting_index, Some(1));
+
+        assert!(sources.contains(Path::new("<string>")));
+    }
+
+    #[test]
+    fn it_reads_long_runs_of_lines() {
+        // Enough lines to overflow the stack if each took a frame
+        let mut journal = "; a comment\n".repeat(200_000);
+        journal.push_str("2020-01-01 Shop\n    Expenses:Food    £10\n    Assets:Current\n");
+
+        let (transactions, _, _) = Reader::new().read_str(&journal, Config::new()).unwrap();
+        assert_eq!(transactions.len(), 1);
+    }
+
+    #[test]
+    fn it_renames_payees_with_aliases() {
+        let journal = "2020-01-01 AMZN Mktp UK
+    Expenses:Shopping    £10
+    Assets:Current
+
+payee Amazon
+    ; comments are allowed
+    alias ^amzn
+    alias amazon\\.co\\.uk
+
+2020-01-02 Amazon.co.uk
+    Expenses:Shopping    £5
+    Assets:Current
+";
+        let mut reader = Reader::new();
+        let (transactions, _, _) = reader.read_str(journal, Config::new()).unwrap();
+
+        let payees: Vec<&str> = transactions.iter().map(|t| t.payee.as_str()).collect();
+        assert_eq!(payees, vec!["Amazon", "Amazon"]);
+        assert_eq!(reader.payees().len(), 1);
+        assert_eq!(reader.payees()[0].aliases, vec!["^amzn", "amazon\\.co\\.uk"]);
+    }
+
+    #[test]
+    fn it_reads_transactions_straight_after_payee_directives() {
+        let journal = "payee Amazon
+    alias ^amzn
+2020-01-01 AMZN Mktp UK
+    Expenses:Shopping    £10
+    Assets:Current
+";
+        let mut reader = Reader::new();
+        let (transactions, _, _) = reader.read_str(journal, Config::new()).unwrap();
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].payee, "Amazon");
+        assert_eq!(reader.payees()[0].aliases, vec!["^amzn"]);
+    }
+
+    #[test]
+    fn it_reads_comments_after_posting_amounts() {
+        let journal = "2020-01-01 Shop  ; :shopping:
+    Expenses:Food    £10.50  ; receipt: in the drawer
+    Assets:Current  ; :cash:
+";
+        let (transactions, postings, _) = Reader::new().read_str(journal, Config::new()).unwrap();
+
+        assert_eq!(postings[0].amount.as_ref().unwrap().quantity, 1050);
+        assert_eq!(postings[0].comments, vec!["receipt: in the drawer"]);
+        assert_eq!(postings[1].amount.as_ref().unwrap().quantity, -1050);
+        assert_eq!(postings[1].comments, vec![":cash:"]);
+
+        let tags: Vec<String> = transactions[0].tags().into_iter().map(|t| t.name).collect();
+        assert_eq!(tags, vec!["shopping"]);
+        assert_eq!(postings[0].tags()[0].value.as_deref(), Some("in the drawer"));
+    }
+
+    #[test]
+    fn it_reports_invalid_aliases() {
+        let journal = "payee Amazon\n    alias (amzn\n";
+        match Reader::new().read_str(journal, Config::new()) {
+            Err(e) => {
+                assert_eq!(e.kind.code(), "invalid-alias");
+                assert_eq!(e.columns, Some(10..15));
+            }
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn it_writes_transactions_with_elided_amounts() {
+        let journal = "2020-01-01 * Shop
+    Expenses:Food    £10.50
+    Assets:Current
+";
+        let (transactions, postings, _) = Reader::new().read_str(journal, Config::new()).unwrap();
+
+        let mut output = String::new();
+        transactions[0].write(&mut output, &postings, false).unwrap();
+        assert_eq!(
+            output,
+            "2020-01-01 * Shop\n    Expenses:Food   £10.50\n    Assets:Current\n"
+        );
+
+        let mut output = String::new();
+        transactions[0].write(&mut output, &postings, true).unwrap();
+        assert_eq!(
+            output,
+            "2020-01-01 * Shop\n    Expenses:Food   £10.50\n    Assets:Current  £-10.50\n"
+        );
+    }
+
+    #[test]
+    fn it_reports_errors_in_strings() {
+        let journal = "2020-01-01 Shop
+    Expenses:Food    £10
+    Assets:Current    £-5
+";
+        match Reader::new().read_str(journal, Config::new()) {
+            Err(e) => assert_eq!(e.to_string(), "<string>:1:1: transaction does not balance"),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn it_resolves_includes_in_strings_from_the_current_directory() {
+        let journal = format!("include {}\n", journal("included.journal").display());
+        let (transactions, _, sources) = Reader::new().read_str(&journal, Config::new()).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(sources.len(), 2);
+
+        match Reader::new().read_str("include included.journal\n", Config::new()) {
+            Err(e) => assert!(matches!(e.kind, ErrorKind::IncludeNotFound(_))),
+            Ok(_) => panic!("expected the include to be resolved from the current directory"),
+        }
+    }
+
+    #[test]
+    fn it_counts_transactions_in_each_source() {
+        let included = journal("included.journal");
+        let journal = format!(
+            "include {}\n2020-01-01 Shop\n    Expenses:Food    £10\n    Assets:Current\n",
+            included.display()
+        );
+        let mut reader = Reader::new();
+        reader.read_str(&journal, Config::new()).unwrap();
+
+        let counts = reader.transaction_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[Path::new("<string>")], 1);
+        assert_eq!(counts[&included], 2);
+    }
+
+    #[test]
+    fn it_reads_price_directives() {
+        let journal = "P 2020-01-01 USD £0.80
+2020-01-02 Shop
+    Expenses:Food    $10
+    Assets:Current
+P 2020/02/01 EUR £0.9 ; a comment
+";
+        let mut reader = Reader::new();
+        reader.read_str(journal, Config::new()).unwrap();
+
+        let prices: Vec<(String, String, Amount)> = reader
+            .prices()
+            .iter()
+            .map(|p| (p.date.to_string(), p.commodity.clone(), p.price.clone()))
+            .collect();
+        assert_eq!(
+            prices,
+            vec![
+                ("2020-01-01".to_owned(), "USD".to_owned(), Amount::new(80, "£")),
+                ("2020-02-01".to_owned(), "EUR".to_owned(), Amount::new(90, "£")),
+            ]
+        );
+
+        match Reader::new().read_str("P 2020-01-01 USD\n", Config::new()) {
+            Err(e) => assert!(matches!(e.kind, ErrorKind::Parse(LineType::PriceDirective))),
+            Ok(_) => panic!("expected a missing price to be an error"),
+        }
+    }
+
+    #[test]
+    fn it_reads_periodic_transactions() {
+        let mut reader = Reader::new();
+        let (transactions, _, _) = reader.read(journal("budget-test.journal"), Config::new()).unwrap();
+        assert_eq!(transactions.len(), 5);
+
+        let month = |y, m| time::Date::try_from_ymd(y, m, 1).unwrap();
+        let periodic: Vec<(Option<time::Date>, Option<time::Date>, &str)> = reader
+            .periodic_transactions()
+            .iter()
+            .map(|p| (p.period.start_date, p.period.end_date, p.postings[0].path.as_str()))
+            .collect();
+        assert_eq!(
+            periodic,
+            vec![
+                (Some(month(2020, 10)), Some(month(2020, 11)), "Clothing"),
+                (Some(month(2020, 11)), Some(month(2020, 12)), "Groceries"),
+            ]
+        );
+
+        let journal = "~ monthly from 2020/12/25 to 2021-02  ; comment
+    ; a comment
+    Expenses:Food    £100
+2020-12-01 Shop
+    Expenses:Food    £10
+    Assets:Cash
+~ monthly
+    Expenses:Rent    £500
+";
+        let (transactions, _, _) = reader.read_str(journal, Config::new()).unwrap();
+        assert_eq!(transactions.len(), 1);
+        let periods: Vec<&Period> = reader.periodic_transactions().iter().map(|p| &p.period).collect();
+        assert_eq!(periods[0].start_date, Some(month(2020, 12)));
+        assert_eq!(periods[0].end_date, Some(month(2021, 2)));
+        assert!(periods[1].includes(month(1999, 1)));
+
+        match Reader::new().read_str("~ weekly\n    Expenses:Food    £10\n", Config::new()) {
+            Err(e) => assert!(matches!(e.kind, ErrorKind::Parse(LineType::PeriodicTransactionHeader))),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn it_reads_a_journal() {
+        let journal = Reader::new()
+            .read_journal(vec![journal("test.journal")], Config::new())
+            .unwrap();
+
+        assert_eq!(journal.transactions().len(), 7);
+        assert_eq!(journal.sources().len(), 2);
+        assert_eq!(journal.sources().values().sum::<usize>(), 7);
+        assert_eq!(journal.commodities().iter().collect::<Vec<_>>(), vec!["£"]);
+
+        let transaction = &journal.transactions()[1];
+        for posting in journal.postings_for(transaction) {
+            assert_eq!(journal.transaction_of(posting), Some(transaction));
+        }
+        assert!(journal.accounts().get_node_at_path(&["Expenses", "Life"]).is_some());
+    }
+
+    #[test]
+    fn it_merges_files_in_order() {
+        let files = vec![journal("included.journal"), journal("age-test.journal")];
+        let (transactions, _, sources) = Reader::new().read_files(files, Config::new()).unwrap();
+
+        assert_eq!(transactions[0].payee, "A restaurant");
+        assert_eq!(transactions[1].payee, "Clothes store");
+        assert_eq!(transactions.len(), 2 + 15);
+        assert_eq!(sources.len(), 2);
+    }
+
+    #[test]
+    fn it_detects_the_same_file_read_twice() {
+        let files = vec![journal("included.journal"), journal("../tests/included.journal")];
+        match Reader::new().read_files(files, Config::new()) {
+            Err(e) => assert!(matches!(e.kind, ErrorKind::DuplicateSource(_))),
+            Ok(_) => panic!("expected a duplicate source"),
+        }
+
+        // Reaching a root through an include is also a duplicate
+        let files = vec![journal("included.journal"), journal("test.journal")];
+        match Reader::new().read_files(files, Config::new()) {
+            Err(e) => {
+                assert!(matches!(e.kind, ErrorKind::DuplicateSource(_)));
+                assert_eq!(e.line, 1);
+            }
+            Ok(_) => panic!("expected a duplicate source"),
+        }
     }
 }
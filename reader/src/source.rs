@@ -1,306 +1,770 @@
-use std::{iter::Peekable, path::PathBuf, str::Chars, sync::mpsc::Sender, thread};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc,
+    },
+    thread,
+};
 
-use journal::{Amount, Posting, Status, Transaction};
+use journal::{Amount, Payee, Period, PeriodInterval, PeriodicTransaction, Posting, Price, Status, Transaction};
+use regex::RegexBuilder;
 
 use super::{
-    bufreader::BufReader,
     error::LineType,
     error::{Error, ErrorKind},
+    include,
 };
 
+/// How many items a source can parse before they are received
+/// Sources wait rather than getting further ahead, so memory use doesn't grow with the size of the journal
+const CHANNEL_BOUND: usize = 1024;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum State {
     None,
     InTransaction,
+    /// After a periodic transaction's header, before its postings
     InPeriodicTransaction,
     InPosting,
+    /// Reading the sub-directives of a payee directive
+    InPayeeDirective,
+    /// Skipping lines after an error until the next blank line or transaction header
+    Recovering,
+}
+
+/// How a source reads its file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Reading {
+    /// A line at a time, so memory use doesn't grow with the size of the file
+    Lines,
+    /// All at once, so lines are slices of the file's contents and reading them doesn't allocate
+    Whole,
 }
 
 pub struct ParsedItem {
     pub location: PathBuf,
+    /// The line on which the item was completed
+    pub line: u64,
     pub kind: ItemKind,
 }
 
 pub enum ItemKind {
     SourceComplete,
     Transaction(Transaction, Vec<Posting>),
-    IncludeDirective(PathBuf),
+    PeriodicTransaction(PeriodicTransaction),
+    PayeeDirective(Payee),
+    PriceDirective(Price),
+    IncludeDirective(Vec<PathBuf>),
+    /// An included file which is being parsed on its own thread. Its items arrive on the receiver.
+    IncludedSource(PathBuf, Receiver<Result<ParsedItem, Error>>),
+}
+
+/// Where a source's lines come from
+enum Contents {
+    Lines(BufReader<Box<dyn Read + Send>>),
+    Whole {
+        contents: Arc<String>,
+        /// The start of the next line
+        position: usize,
+        /// An error in reading the contents, which is returned once the lines before it have been
+        error: Option<io::Error>,
+    },
+}
+
+impl Contents {
+    fn new(contents: Box<dyn Read + Send>, reading: Reading) -> Self {
+        match reading {
+            Reading::Lines => Contents::Lines(BufReader::new(contents)),
+            Reading::Whole => Contents::whole(contents),
+        }
+    }
+
+    /// Reads everything in `contents`, up to the line with the first error in it
+    fn whole(mut contents: Box<dyn Read + Send>) -> Self {
+        let mut bytes = Vec::new();
+        let error = contents.read_to_end(&mut bytes).err();
+
+        let (contents, error) = match String::from_utf8(bytes) {
+            Ok(contents) => (contents, error),
+            Err(e) => {
+                // Reading a line at a time reports invalid UTF-8 on its line, so the lines before it are kept
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(bytes[..valid].iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1));
+                let contents = String::from_utf8(bytes).expect("bytes before valid_up_to are valid");
+                let error = io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+                (contents, Some(error))
+            }
+        };
+
+        Contents::Whole {
+            contents: Arc::new(contents),
+            position: 0,
+            error,
+        }
+    }
+}
+
+/// A line of a source without its newline, which shares its buffer with other lines
+#[derive(Clone, Default)]
+struct Line {
+    buffer: Arc<String>,
+    range: Range<usize>,
+}
+
+impl Line {
+    fn as_str(&self) -> &str {
+        &self.buffer[self.range.clone()]
+    }
 }
 
+/// Parses a journal file a line at a time
 pub struct Source {
-    // TODO see if we can remove this Arc until it's being passed between threads
     location: PathBuf,
-    contents: BufReader,
+    contents: Contents,
+    /// How included files are read
+    reading: Reading,
     state: State,
     line: u64,
+    /// The current line. When reading a line at a time, its buffer is reused for the next line unless it's still
+    /// referred to.
+    text: Line,
     transaction: Option<Transaction>,
+    /// The line number and text of the current transaction's header
+    transaction_line: (u64, Line),
     postings: Vec<Posting>,
+    /// The periodic transaction being read, whose postings are in `postings` until it is finished
+    periodic_transaction: Option<PeriodicTransaction>,
+    /// The payee directive being read
+    payee: Option<Payee>,
+    /// A price directive which has been read but not yet sent
+    price: Option<Price>,
+    /// An error in the line after an item, which is returned once that item has been
+    held_error: Option<Error>,
+    /// Whether to carry on parsing after an error
+    recover: bool,
 }
 
 impl Source {
-    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+    pub fn new<P: Into<PathBuf>>(path: P, recover: bool, reading: Reading) -> Result<Self, Error> {
         let path = path.into();
 
-        return Self {
-            location: path.clone(),
-            contents: BufReader::open(&path).unwrap(),
+        let contents = match File::open(&path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(Error::new(ErrorKind::IO(e), path, 0)),
+        };
+
+        Ok(Self::from_reader(path, contents, recover, reading))
+    }
+
+    /// Creates a source which reads from `contents` as if it were the file at `location`
+    pub fn from_reader<P: Into<PathBuf>>(
+        location: P,
+        contents: impl Read + Send + 'static,
+        recover: bool,
+        reading: Reading,
+    ) -> Self {
+        Self {
+            location: location.into(),
+            contents: Contents::new(Box::new(contents), reading),
+            reading,
             state: State::None,
             line: 0,
+            text: Line::default(),
             transaction: None,
+            transaction_line: (0, Line::default()),
             postings: Vec::with_capacity(10),
-        };
+            periodic_transaction: None,
+            payee: None,
+            price: None,
+            held_error: None,
+            recover,
+        }
+    }
+
+    /// Parses the file at `path` on a new thread and returns the receiving end of its results
+    pub fn spawn(path: PathBuf, recover: bool, reading: Reading) -> Receiver<Result<ParsedItem, Error>> {
+        Source::spawn_with(move || Source::new(path, recover, reading))
+    }
+
+    /// Parses `contents` on a new thread as if it were the file at `location`
+    pub fn spawn_reader(
+        location: PathBuf,
+        contents: impl Read + Send + 'static,
+        recover: bool,
+        reading: Reading,
+    ) -> Receiver<Result<ParsedItem, Error>> {
+        Source::spawn_with(move || Ok(Source::from_reader(location, contents, recover, reading)))
+    }
+
+    fn spawn_with<F>(source: F) -> Receiver<Result<ParsedItem, Error>>
+    where
+        F: FnOnce() -> Result<Source, Error> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_BOUND);
+
+        thread::spawn(move || match source() {
+            Ok(mut source) => source.parse(sender),
+            Err(e) => {
+                // If the receiver has hung up, there's nobody to tell
+                let _ = sender.send(Err(e));
+            }
+        });
+
+        receiver
     }
 
     /// Wraps a ParsedItem with the location of this source
     fn new_item(&self, kind: ItemKind) -> ParsedItem {
         ParsedItem {
             location: self.location.clone(),
+            line: self.line,
             kind,
         }
     }
 
-    /// Wraps an ErrorKind with the location of this source
-    // TODO move this into the Error file
+    /// Wraps an ErrorKind with the location of this source and the current line
     fn new_error(&self, kind: ErrorKind) -> Error {
-        Error {
-            location: self.location.clone(),
-            line: self.line,
-            kind,
-        }
+        Error::new(kind, self.location.clone(), self.line).with_text(self.text())
     }
 
-    pub fn parse(&mut self, sender: Sender<Result<ParsedItem, Error>>) {
-        let result = self.parse_line();
-        let mut should_continue = true;
-
-        match &result {
-            Err(_) => should_continue = false,
-            Ok(result) => match &result.kind {
-                ItemKind::Transaction(_, _) => {}
-                ItemKind::SourceComplete => should_continue = false,
-                ItemKind::IncludeDirective(include) => {
-                    let send = sender.clone();
-                    let include = include.clone();
-                    thread::spawn(move || {
-                        let mut source = Source::new(include.as_path());
-                        source.parse(send);
-                    });
+    /// Wraps an ErrorKind with the location of this source and the given columns of the current line
+    fn new_error_at(&self, kind: ErrorKind, columns: Range<usize>) -> Error {
+        self.new_error(kind).with_columns(columns)
+    }
+
+    /// Wraps an ErrorKind with the location of the current transaction's header
+    fn new_transaction_error(&self, kind: ErrorKind) -> Error {
+        let (line, text) = &self.transaction_line;
+        Error::new(kind, self.location.clone(), *line).with_text(text.as_str())
+    }
+
+    /// The text of the current line
+    fn text(&self) -> &str {
+        self.text.as_str()
+    }
+
+    /// The columns of `rest`, which ends the current line, ignoring trailing space
+    fn rest_of_line(&self, rest: &str) -> Range<usize> {
+        let text = self.text();
+        let length = text.chars().count();
+        let trailing = text.chars().rev().take_while(|c| c.is_whitespace()).count();
+        let start = text[..text.len() - rest.len()].chars().count();
+        start..(length - trailing).max(start)
+    }
+
+    /// Makes the next line the current one, returning false if there are no more
+    fn next_line(&mut self) -> io::Result<bool> {
+        match &mut self.contents {
+            Contents::Lines(contents) => {
+                // The header of a transaction keeps its line, so that line's buffer can't be reused
+                if Arc::get_mut(&mut self.text.buffer).is_none() {
+                    self.text.buffer = Arc::default();
                 }
-            },
-        }
+                let buffer = Arc::make_mut(&mut self.text.buffer);
+                buffer.clear();
 
-        match sender.send(result) {
-            Ok(_) => {
-                if should_continue {
-                    self.parse(sender);
+                if contents.read_line(buffer)? == 0 {
+                    return Ok(false);
+                }
+                if buffer.ends_with('\n') {
+                    buffer.pop();
+                }
+                self.text.range = 0..buffer.len();
+            }
+            Contents::Whole {
+                contents,
+                position,
+                error,
+            } => {
+                if *position == contents.len() {
+                    return error.take().map_or(Ok(false), Err);
                 }
+
+                let end = contents[*position..]
+                    .find('\n')
+                    .map_or(contents.len(), |i| *position + i);
+                self.text = Line {
+                    buffer: Arc::clone(contents),
+                    range: *position..end,
+                };
+                *position = (end + 1).min(contents.len());
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Sends every item in this source to `sender`, stopping after the first error unless recovering
+    pub fn parse(&mut self, sender: SyncSender<Result<ParsedItem, Error>>) {
+        loop {
+            let result = self.parse_line();
+
+            let should_continue = match &result {
+                Err(e) => self.recover && self.recover_from(e),
+                Ok(item) => !matches!(item.kind, ItemKind::SourceComplete),
+            };
+
+            let sent = match result {
+                // Included files are parsed in parallel, each into its own channel. The receivers are sent on
+                // in the order they were included so the reader can drain them in place of the directive.
+                Ok(ParsedItem {
+                    kind: ItemKind::IncludeDirective(includes),
+                    location,
+                    line,
+                }) => includes.into_iter().all(|include| {
+                    let receiver = Source::spawn(include.clone(), self.recover, self.reading);
+                    let item = ParsedItem {
+                        location: location.clone(),
+                        line,
+                        kind: ItemKind::IncludedSource(include, receiver),
+                    };
+                    sender.send(Ok(item)).is_ok()
+                }),
+                result => sender.send(result).is_ok(),
+            };
+
+            // If the receiver has hung up, there's no point in continuing
+            if !sent || !should_continue {
+                return;
             }
-            Err(_) => {
-                // println!("Error while bloop parsing: {}", e);
+        }
+    }
+
+    /// Prepares to carry on parsing after `error`, returning false if that isn't possible
+    fn recover_from(&mut self, error: &Error) -> bool {
+        match error.kind {
+            // Reading on after an IO error is unlikely to help
+            ErrorKind::IO(_) => false,
+
+            // These are found once the transaction has been closed or outside of one so there's nothing to skip
+            ErrorKind::TransactionDoesNotBalance
+            | ErrorKind::TwoPostingsWithElidedAmounts
+            | ErrorKind::IncludeNotFound(_)
+            | ErrorKind::InvalidInclude(_)
+            | ErrorKind::InvalidAlias(_) => true,
+
+            // Otherwise, drop the transaction or directive we were in and skip the rest of it
+            _ => {
+                self.transaction = None;
+                self.postings.clear();
+                self.periodic_transaction = None;
+                self.payee = None;
+                self.price = None;
+                self.state = State::Recovering;
+                true
+            }
+        }
+    }
+
+    /// Finishes the directive or transaction being read, if there is one
+    fn close_item(&mut self) -> Option<Result<ParsedItem, Error>> {
+        if let Some(payee) = self.payee.take() {
+            return Some(Ok(self.new_item(ItemKind::PayeeDirective(payee))));
+        }
+        if let Some(price) = self.price.take() {
+            return Some(Ok(self.new_item(ItemKind::PriceDirective(price))));
+        }
+        if let Some(mut periodic_transaction) = self.periodic_transaction.take() {
+            // Periodic transactions needn't balance
+            periodic_transaction.postings = std::mem::replace(&mut self.postings, Vec::with_capacity(10));
+            return Some(Ok(self.new_item(ItemKind::PeriodicTransaction(periodic_transaction))));
+        }
+        self.close_transaction()
+    }
+
+    /// Returns the item finished before `error` was found, holding the error back until the next line is asked for
+    /// so that a bad header or directive doesn't lose the item before it
+    fn hold_error(&mut self, result: Option<Result<ParsedItem, Error>>, error: Error) -> Result<ParsedItem, Error> {
+        match result {
+            None => Err(error),
+            Some(result) => {
+                self.held_error = Some(error);
+                result
             }
         }
     }
 
     /// Checks that the transaction balances (and handles an elided amount)
-    fn close_transaction(&mut self) -> Option<Result<ParsedItem, ErrorKind>> {
+    fn close_transaction(&mut self) -> Option<Result<ParsedItem, Error>> {
         match self.transaction.take() {
-            None => return None,
-            Some(transaction) => {
+            None => None,
+            Some(mut transaction) => {
                 let mut elided_index = None;
                 let mut sum = 0_i64;
-                let mut postings = Vec::with_capacity(self.postings.len());
+                let mut postings = std::mem::replace(&mut self.postings, Vec::with_capacity(10));
 
-                for (i, posting) in self.postings.drain(0..).enumerate() {
+                for (i, posting) in postings.iter().enumerate() {
                     match &posting.amount {
                         Some(amount) => sum += amount.quantity,
                         None => {
                             if elided_index.is_some() {
-                                return Some(Err(ErrorKind::TwoPostingsWithElidedAmounts));
+                                return Some(Err(self.new_transaction_error(ErrorKind::TwoPostingsWithElidedAmounts)));
                             }
                             elided_index = Some(i);
                         }
                     }
-                    postings.push(posting);
-                }
-
-                // The transaction is balanced so return it
-                if sum == 0 {
-                    return Some(Ok(self.new_item(ItemKind::Transaction(transaction, postings))));
                 }
 
                 match elided_index {
+                    // The transaction is balanced so return it
+                    None if sum == 0 => {}
                     // If there is no posting with an elided amount, we can't balance the transaction
-                    None => return Some(Err(ErrorKind::TransactionDoesNotBalance)),
-                    // TODO assign a commodity to this amount
-                    Some(i) => postings[i].amount = Some(Amount::new(-sum, "")),
+                    None => return Some(Err(self.new_transaction_error(ErrorKind::TransactionDoesNotBalance))),
+                    Some(i) => {
+                        // TODO handle transactions with more than one commodity
+                        let commodity = postings
+                            .iter()
+                            .find_map(|p| p.amount.as_ref())
+                            .map(|a| a.commodity.clone())
+                            .unwrap_or_default();
+                        postings[i].amount = Some(Amount::new(-sum, &commodity));
+                        transaction.elided_amount_posting_index = Some(i);
+                    }
                 }
 
-                return Some(Ok(self.new_item(ItemKind::Transaction(transaction, postings))));
+                Some(Ok(self.new_item(ItemKind::Transaction(transaction, postings))))
             }
         }
     }
 
+    /// Reads lines until an item is complete
     fn parse_line(&mut self) -> Result<ParsedItem, Error> {
-        match self.contents.next() {
-            None => {
-                // If the source is complete, we need to finish the last transactions
-                if let Some(result) = self.close_transaction() {
-                    return result.map_err(|kind| self.new_error(kind));
+        if let Some(error) = self.held_error.take() {
+            return Err(error);
+        }
+
+        loop {
+            match self.next_line() {
+                // Invalid UTF-8 is reported here too, on the line it's in
+                Err(e) => return Err(Error::new(ErrorKind::IO(e), self.location.clone(), self.line + 1)),
+                Ok(false) => {
+                    // If the source is complete, we need to finish the last transactions
+                    if let Some(result) = self.close_item() {
+                        return result;
+                    }
+
+                    return Ok(self.new_item(ItemKind::SourceComplete));
                 }
+                Ok(true) => self.line += 1,
+            }
 
-                return Ok(self.new_item(ItemKind::SourceComplete));
+            // The line is borrowed from this rather than from self so it can be read while self is changed
+            let text = self.text.clone();
+            let line = text.as_str();
+
+            let first = line.chars().next();
+
+            // After an error, skip everything up to the next blank line or transaction header
+            if self.state == State::Recovering {
+                match first {
+                    Some(c) if !c.is_numeric() => continue,
+                    _ => self.state = State::None,
+                }
             }
-            Some(line) => match line {
-                Err(e) => return Err(self.new_error(ErrorKind::IO(e))),
-                Ok(line) => {
-                    self.line += 1;
 
-                    let mut iter = line.chars().peekable();
+            match first {
+                // Empty line
+                None => {
+                    self.state = State::None;
 
-                    match iter.peek() {
-                        // Empty line
+                    // If the line is empty, we need to finish the previous transaction
+                    if let Some(result) = self.close_item() {
+                        return result;
+                    }
+                }
+
+                // Transaction header
+                Some(c) if c.is_numeric() => {
+                    // A header can follow a complete transaction or directive without a blank line in between
+                    if self.state == State::InTransaction {
+                        return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::TransactionHeader)));
+                    }
+                    self.state = State::InTransaction;
+
+                    let result = self.close_item();
+                    let new_transaction = match self.parse_transaction_header(line) {
+                        Ok(transaction) => transaction,
+                        Err(e) => return self.hold_error(result, e),
+                    };
+                    self.transaction = Some(new_transaction);
+                    self.transaction_line = (self.line, self.text.clone());
+
+                    if let Some(result) = result {
+                        return result;
+                    }
+                }
+
+                // Posting or comment
+                Some(c) if c.is_whitespace() => {
+                    let (spaces, rest) = consume_space(line);
+                    if spaces < 2 {
+                        let indent = 0..self.rest_of_line(rest).start;
+                        return Err(self.new_error_at(
+                            ErrorKind::IncorrectFormatting("not enough spaces beginning line".to_string()),
+                            indent,
+                        ));
+                    }
+
+                    let next = match rest.chars().next() {
+                        Some(next) => next,
                         None => {
-                            // If the line is empty, we need to finish the previous transaction
-                            if let Some(result) = self.close_transaction() {
-                                return result.map_err(|kind| self.new_error(kind));
+                            return Err(self.new_error_at(
+                                ErrorKind::IncorrectFormatting("not enough remaining characters".to_string()),
+                                0..self.text().chars().count(),
+                            ))
+                        }
+                    };
+
+                    // Handle comment
+                    if is_comment_indicator(next) {
+                        // Skip the comment indicator
+                        if let Some(comment) = parse_comment(&rest[1..]) {
+                            let comment = comment.to_owned();
+                            match self.state {
+                                State::InPosting => match self.postings.last_mut() {
+                                    None => return Err(self.new_error(ErrorKind::MissingPosting)),
+                                    Some(posting) => posting.add_comment(comment),
+                                },
+
+                                State::InTransaction => match &mut self.transaction {
+                                    None => return Err(self.new_error(ErrorKind::MissingTransaction)),
+                                    Some(transaction) => transaction.comments.push(comment),
+                                },
+
+                                // There's nothing to attach these to
+                                State::InPayeeDirective | State::InPeriodicTransaction => {}
+
+                                _ => return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::Comment))),
                             }
-
-                            self.state = State::None;
-                            self.parse_line()
                         }
 
-                        // Transaction header
-                        Some(c) if c.is_numeric() => {
-                            if self.state != State::None && self.state != State::InPosting {
-                                return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::TransactionHeader)));
-                            }
-                            self.state = State::InTransaction;
+                        continue;
+                    }
 
-                            let new_transaction = self.parse_transaction_header(&mut iter)?;
+                    if self.state == State::InPayeeDirective {
+                        self.parse_payee_subdirective(rest)?;
+                        continue;
+                    }
 
-                            if let Some(result) = self.close_transaction() {
-                                self.transaction = Some(new_transaction);
-                                return result.map_err(|kind| self.new_error(kind));
-                            } else {
-                                self.transaction = Some(new_transaction);
-                                self.parse_line()
-                            }
-                        }
+                    // Handle posting
+                    if self.state == State::None {
+                        return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::Posting)));
+                    }
+                    self.state = State::InPosting;
 
-                        // Posting or comment
-                        Some(c) if c.is_whitespace() => {
-                            if consume_space(&mut iter) < 2 {
-                                return Err(self.new_error(ErrorKind::IncorrectFormatting(
-                                    "not enough spaces beginning line".to_string(),
-                                )));
-                            }
+                    let posting = self.parse_posting(rest)?;
+                    self.postings.push(posting);
+                }
 
-                            let next = iter.peek().ok_or(self.new_error(ErrorKind::IncorrectFormatting(
-                                "not enough remaining characters".to_string(),
-                            )))?;
-
-                            // Handle comment
-                            if is_comment_indicator(next) {
-                                // Advance past the comment indicator
-                                iter.next();
-
-                                match self.parse_comment(&mut iter) {
-                                    None => return self.parse_line(),
-                                    Some(comment) => match self.state {
-                                        State::InPosting => match &mut self.postings.last_mut() {
-                                            None => return Err(self.new_error(ErrorKind::MissingPosting)),
-                                            Some(posting) => posting.add_comment(comment),
-                                        },
-
-                                        State::InTransaction => match &mut self.transaction {
-                                            None => return Err(self.new_error(ErrorKind::MissingTransaction)),
-                                            Some(transaction) => transaction.comments.push(comment),
-                                        },
-
-                                        // TODO handle comments in periodic transactions
-                                        _ => return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::Comment))),
-                                    },
-                                }
-
-                                return self.parse_line();
-                            }
+                // Include directive
+                Some('i') => {
+                    let (include, columns) = self.parse_include_directive(line)?;
+                    let parent = self.location.parent().unwrap_or_else(|| Path::new(""));
+                    let includes = include::resolve(include, parent, &self.location)
+                        .map_err(|kind| self.new_error_at(kind, columns))?;
 
-                            // Handle posting
-                            if self.state == State::None {
-                                return Err(self.new_error(ErrorKind::UnexpectedItem(LineType::Posting)));
-                            }
-                            self.state = State::InPosting;
+                    return Ok(self.new_item(ItemKind::IncludeDirective(includes)));
+                }
 
-                            let posting = self.parse_posting(&mut iter)?;
-                            self.postings.push(posting);
+                // Payee directive
+                Some('p') => {
+                    let (directive, rest) = take_to_space(line);
+                    if directive != "payee" {
+                        // Like other unknown lines, this is ignored
+                        continue;
+                    }
 
-                            self.parse_line()
-                        }
+                    let result = self.close_item();
+                    let name = consume_space(rest).1.trim();
+                    if name.is_empty() {
+                        let error = self.new_error(ErrorKind::Parse(LineType::PayeeDirective));
+                        return self.hold_error(result, error);
+                    }
 
-                        // Include directive
-                        Some(c) if c == &'i' => {
-                            let include = self.parse_include_directive(&mut iter)?;
-                            match self.location.clone().parent() {
-                                None => panic!("no parent"),
-                                Some(parent) => {
-                                    return Ok(self.new_item(ItemKind::IncludeDirective(parent.join(include))));
-                                }
-                            }
-                        }
+                    self.payee = Some(Payee::new(name.to_owned()));
+                    self.state = State::InPayeeDirective;
 
-                        // File comment
-                        Some(c) if c == &';' => self.parse_line(),
+                    if let Some(result) = result {
+                        return result;
+                    }
+                }
 
-                        // Periodic transaction
-                        // TODO handle periodic transactions
-                        Some(c) if c == &'~' => {
-                            unimplemented!();
-                        }
+                // Price directive
+                Some('P') => {
+                    let result = self.close_item();
+                    let price = match self.parse_price_directive(line) {
+                        Ok(price) => price,
+                        Err(e) => return self.hold_error(result, e),
+                    };
+                    self.price = Some(price);
+                    self.state = State::None;
+
+                    if let Some(result) = result {
+                        return result;
+                    }
+                }
 
-                        // Unmatched line type
-                        _ => self.parse_line(),
+                // Periodic transaction
+                Some('~') => {
+                    let result = self.close_item();
+                    let period = match self.parse_period(&line[1..]) {
+                        Ok(period) => period,
+                        Err(e) => return self.hold_error(result, e),
+                    };
+                    self.periodic_transaction = Some(PeriodicTransaction {
+                        period,
+                        postings: Vec::new(),
+                    });
+                    self.state = State::InPeriodicTransaction;
+
+                    if let Some(result) = result {
+                        return result;
                     }
                 }
+
+                // File comments and unmatched line types are ignored
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads an indented line of a payee directive, of which only `alias` is understood
+    fn parse_payee_subdirective(&mut self, line: &str) -> Result<(), Error> {
+        let (subdirective, rest) = take_to_space(line);
+        if subdirective != "alias" {
+            return Ok(());
+        }
+
+        let (_, rest) = consume_space(rest);
+        let columns = self.rest_of_line(rest);
+        let alias = rest.trim();
+
+        // Check the pattern now so the error can point at it
+        if let Err(e) = RegexBuilder::new(alias).build() {
+            return Err(self.new_error_at(ErrorKind::InvalidAlias(e.to_string()), columns));
+        }
+
+        if let Some(payee) = &mut self.payee {
+            payee.aliases.push(alias.to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Reads a price directive, such as `P 2020-01-01 USD £0.80`
+    fn parse_price_directive(&self, line: &str) -> Result<Price, Error> {
+        let error = || self.new_error(ErrorKind::Parse(LineType::PriceDirective));
+
+        let (directive, rest) = take_to_space(line);
+        if directive != "P" {
+            return Err(error());
+        }
+        let (date, rest) = take_to_space(consume_space(rest).1);
+        let date = self.parse_date(date).map_err(|_| error())?;
+        let (commodity, rest) = take_to_space(consume_space(rest).1);
+
+        let (text, _) = take_to_comment(consume_space(rest).1);
+        let (price_commodity, quantity) = take_to_number(text.trim_end());
+        if commodity.is_empty() || quantity.is_empty() {
+            return Err(error());
+        }
+        let quantity = parse_quantity(quantity).ok_or_else(error)?;
+
+        Ok(Price {
+            date,
+            commodity: commodity.to_owned(),
+            price: Amount::new(quantity, price_commodity),
+        })
+    }
+
+    /// Reads the period of a periodic transaction, which is either a month (e.g. `2020-10`) or `monthly` with
+    /// optional `from` and `to` months, e.g. `monthly from 2020-10 to 2021-01`
+    /// Only monthly periods are understood as they are all budgets use
+    fn parse_period(&self, text: &str) -> Result<Period, Error> {
+        let error = || self.new_error(ErrorKind::Parse(LineType::PeriodicTransactionHeader));
+        let month_of = |date: &str| {
+            let date = self.parse_date(date).map_err(|_| error())?;
+            time::Date::try_from_ymd(date.year(), date.month(), 1).map_err(|_| error())
+        };
+
+        let (text, _) = take_to_comment(text);
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let mut period = Period {
+            interval: Some(PeriodInterval::Monthly),
+            frequency: 1,
+            ..Period::default()
+        };
+        match words.as_slice() {
+            ["monthly", rest @ ..] => match rest {
+                [] => {}
+                ["from", start] => period.start_date = Some(month_of(start)?),
+                ["to", end] => period.end_date = Some(month_of(end)?),
+                ["from", start, "to", end] => {
+                    period.start_date = Some(month_of(start)?);
+                    period.end_date = Some(month_of(end)?);
+                }
+                _ => return Err(error()),
             },
+            [month] => {
+                let start = month_of(month)?;
+                period.start_date = Some(start);
+                period.end_date = Some(next_month(start));
+            }
+            _ => return Err(error()),
         }
+
+        Ok(period)
     }
 
-    fn parse_include_directive(&mut self, iter: &mut Peekable<Chars>) -> Result<String, Error> {
-        let include = take_to_space(iter);
-        if include != "include" {
-            return Err(self.new_error(ErrorKind::Parse(LineType::IncludeDirective)));
+    /// Returns the included path and the columns it occupies
+    fn parse_include_directive<'l>(&self, line: &'l str) -> Result<(&'l str, Range<usize>), Error> {
+        let (directive, rest) = take_to_space(line);
+        if directive != "include" {
+            return Err(self.new_error_at(
+                ErrorKind::Parse(LineType::IncludeDirective),
+                0..directive.chars().count(),
+            ));
         }
-        Ok(take_to_end(iter))
+
+        let (_, rest) = consume_space(rest);
+        Ok((rest, self.rest_of_line(rest)))
     }
 
-    fn parse_transaction_header(&mut self, iter: &mut Peekable<Chars>) -> Result<Transaction, Error> {
-        let date = self.parse_date(take_to_space(iter))?;
+    fn parse_transaction_header(&self, line: &str) -> Result<Transaction, Error> {
+        let (date, rest) = take_to_space(line);
 
         let mut transaction = Transaction::new();
-        transaction.date = date;
+        transaction.date = self.parse_date(date)?;
 
-        consume_space(iter);
+        let (_, mut rest) = consume_space(rest);
 
-        match iter.peek() {
+        transaction.status = match rest.chars().next() {
             Some(c) if is_status(c) => {
-                transaction.status = match c {
-                    &'*' => Status::Cleared,
-                    &'!' => Status::Uncleared,
-                    _ => Status::NoStatus,
-                };
-                iter.next();
-                consume_space(iter);
+                rest = consume_space(&rest[1..]).1;
+                match c {
+                    '*' => Status::Cleared,
+                    _ => Status::Uncleared,
+                }
             }
-            _ => transaction.status = Status::NoStatus,
-        }
+            _ => Status::NoStatus,
+        };
 
-        transaction.payee = take_to_comment_or_end(iter).trim_end().to_owned();
-        transaction.header_comment = self.parse_comment(iter);
+        let (payee, comment) = take_to_comment(rest);
+        transaction.payee = payee.trim_end().to_owned();
+        transaction.header_comment = parse_comment(comment).map(str::to_owned);
 
-        return Ok(transaction);
+        Ok(transaction)
     }
 
-    fn parse_date(&self, s: String) -> Result<time::Date, Error> {
+    fn parse_date(&self, s: &str) -> Result<time::Date, Error> {
+        let error = || self.new_error_at(ErrorKind::Parse(LineType::TransactionHeader), 0..s.chars().count());
+
         let mut year: Option<i32> = None;
         let mut month: Option<u8> = None;
         let mut day: Option<u8> = None;
@@ -312,170 +776,177 @@ impl Source {
                         0 => year = component.parse().ok(),
                         1 => month = component.parse().ok(),
                         2 => day = component.parse().ok(),
-                        _ => panic!("too many components"),
+                        _ => return Err(error()),
                     }
                 }
                 break;
             }
         }
 
-        return match (year, month, day) {
+        match (year, month, day) {
             (Some(y), Some(m), Some(d)) => time::Date::try_from_ymd(y, m, d),
             (Some(y), Some(m), None) => time::Date::try_from_ymd(y, m, 1),
             (Some(y), None, None) => time::Date::try_from_ymd(y, 1, 1),
-            _ => unimplemented!(),
+            _ => return Err(error()),
         }
-        .map_err(|_| self.new_error(ErrorKind::Parse(LineType::TransactionHeader)));
+        .map_err(|_| error())
     }
 
-    fn parse_posting(&mut self, iter: &mut Peekable<Chars>) -> Result<Posting, Error> {
-        // FIXME there should be a way of doing this that doesn't introduce a new allocation
-        let account = take_to_multispace(iter).trim_end().to_owned();
+    fn parse_posting(&self, line: &str) -> Result<Posting, Error> {
+        let (account, rest) = take_to_multispace(line);
+        let (_, rest) = consume_space(rest);
 
-        consume_space(iter);
-
-        let commodity = take_to_number(iter);
-
-        let mut quantity: Option<i64> = None;
-        let parsed = take_to_end(iter);
-        if parsed.len() > 0 {
-            quantity = Some(parse_quantity(parsed).map_err(|_| self.new_error(ErrorKind::Parse(LineType::Posting)))?);
-        }
+        // A comment can follow the amount on the same line
+        let (amount_text, comment) = match rest.find(is_comment_indicator) {
+            None => (rest, None),
+            Some(i) => (&rest[..i], Some(rest[i + 1..].trim())),
+        };
+        let amount_text = amount_text.trim_end();
+
+        let (commodity, quantity) = take_to_number(amount_text);
+        let quantity = match quantity {
+            "" => None,
+            quantity => Some(parse_quantity(quantity).ok_or_else(|| {
+                let start = self.rest_of_line(rest).start;
+                let columns = start..start + amount_text.chars().count();
+                self.new_error_at(ErrorKind::Parse(LineType::Posting), columns)
+            })?),
+        };
 
         let mut posting = Posting {
-            path: account,
-            amount: None,
+            path: account.trim_end().to_owned(),
+            amount: quantity.map(|q| Amount::new(q, commodity)),
             comments: vec![],
             transaction: None,
         };
 
-        match quantity {
-            None => {}
-            Some(q) => posting.amount = Some(Amount::new(q, &commodity)),
+        if let Some(comment) = comment {
+            posting.add_comment(comment.to_owned());
         }
 
-        return Ok(posting);
+        Ok(posting)
     }
+}
 
-    /// If the comment's length is 0, this will return None
-    fn parse_comment(&mut self, iter: &mut Peekable<Chars>) -> Option<String> {
-        consume_space(iter);
-        let comment = take_to_end(iter);
-        match comment.len() {
-            0 => None,
-            _ => Some(comment),
-        }
+/// The first day of the month after the one starting on `month`
+fn next_month(month: time::Date) -> time::Date {
+    match month.month() {
+        12 => time::Date::try_from_ymd(month.year() + 1, 1, 1),
+        m => time::Date::try_from_ymd(month.year(), m + 1, 1),
     }
+    .unwrap()
 }
 
-fn parse_quantity(s: String) -> Result<i64, std::num::ParseIntError> {
+/// The text of a comment after its indicator, or None if it is empty
+fn parse_comment(text: &str) -> Option<&str> {
+    match consume_space(text).1 {
+        "" => None,
+        comment => Some(comment),
+    }
+}
+
+/// Parses an amount's quantity in hundredths
+/// Quantities without a decimal point are whole units and those with more than two decimal places aren't rounded
+fn parse_quantity(s: &str) -> Option<i64> {
     let s = s.trim_end();
-    // NB taking len() may not work with non-ASCII strings
-    let length = s.len() - 1;
+    let (negative, digits) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
 
-    return match s.find(".") {
-        None => {
-            let q: i64 = s.parse()?;
+    let mut quantity: i64 = 0;
+    let mut has_digits = false;
+    for b in digits.bytes() {
+        let digit = match b {
+            b'.' => continue,
+            b'0'..=b'9' => i64::from(b - b'0'),
+            _ => return None,
+        };
+        quantity = quantity.checked_mul(10)?;
+        quantity = match negative {
+            true => quantity.checked_sub(digit)?,
+            false => quantity.checked_add(digit)?,
+        };
+        has_digits = true;
+    }
+    if !has_digits {
+        return None;
+    }
 
-            // If the input did not contain a decimal, we need to multiply by 100 so it matches others
-            // e.g. "10" => 10 but "10.00" => 1000
-            Ok(q * 100)
-        }
-        Some(index) => {
-            let multiplier = match length - index {
-                0 => 100, // e.g., "10."
-                1 => 10,  // e.g., "10.0"
-                _ => 1,   // e.g., "10.00"
-            };
-            let q: i64 = s.replace(".", "").parse()?;
-            return Ok(q * multiplier);
-        }
+    // e.g. "10" and "10." => 1000, "10.0" => 1000 and "10.00" => 1000
+    let multiplier = match s.find('.') {
+        None => 100,
+        Some(index) => match s.len() - 1 - index {
+            0 => 100,
+            1 => 10,
+            _ => 1,
+        },
     };
+    quantity.checked_mul(multiplier)
 }
 
-fn take_to_multispace(iter: &mut Peekable<Chars>) -> String {
-    iter.scan("", |state, c| {
-        if c == '\t' {
-            return None;
-        }
-
-        if c == ' ' {
-            if state == &" " {
-                return None;
-            }
-            *state = " ";
-        } else {
-            *state = "";
+/// Splits `text` before the first tab or pair of spaces
+fn take_to_multispace(text: &str) -> (&str, &str) {
+    let bytes = text.as_bytes();
+    for (i, b) in bytes.iter().enumerate() {
+        if *b == b'\t' || (*b == b' ' && bytes.get(i + 1) == Some(&b' ')) {
+            return text.split_at(i);
         }
-
-        Some(c)
-    })
-    .collect()
-}
-
-fn take_to_space(iter: &mut Peekable<Chars>) -> String {
-    iter.take_while(|c| whitespace_size(c) == 0).collect()
-}
-
-fn take_to_comment_or_end(iter: &mut Peekable<Chars>) -> String {
-    // TODO this implementation means that we have to trim space from the end of payees (+1
-    // allocation) - is there a better way?
-    iter.take_while(|c| !is_comment_indicator(c)).collect()
+    }
+    (text, "")
 }
 
-fn take_to_end(iter: &mut Peekable<Chars>) -> String {
-    iter.collect()
+/// Splits `text` before the first space or tab
+fn take_to_space(text: &str) -> (&str, &str) {
+    match text.find(|c| whitespace_size(c) > 0) {
+        Some(i) => text.split_at(i),
+        None => (text, ""),
+    }
 }
 
-fn take_to_number(iter: &mut Peekable<Chars>) -> String {
-    take_until(iter, |c| c.is_numeric() || c == &'+' || c == &'-', "".to_string())
+/// Splits `text` around the first comment indicator, returning everything if there isn't one
+fn take_to_comment(text: &str) -> (&str, &str) {
+    match text.find(is_comment_indicator) {
+        Some(i) => (&text[..i], &text[i + 1..]),
+        None => (text, ""),
+    }
 }
 
-fn take_until(iter: &mut Peekable<Chars>, predicate: fn(&char) -> bool, mut buffer: String) -> String {
-    match iter.peek() {
-        None => buffer,
-        Some(c) => {
-            if predicate(c) {
-                buffer
-            } else {
-                buffer.push(*c);
-                iter.next();
-                take_until(iter, predicate, buffer)
-            }
-        }
+/// Splits `text` before the start of the first number
+fn take_to_number(text: &str) -> (&str, &str) {
+    match text.find(|c: char| c.is_numeric() || c == '+' || c == '-') {
+        Some(i) => text.split_at(i),
+        None => (text, ""),
     }
 }
 
-/// Consumes spaces in the iterator and returns how many it did
-fn consume_space(iter: &mut Peekable<Chars>) -> u8 {
-    match iter.peek() {
-        None => return 0,
-        Some(c) => {
-            let space_size = whitespace_size(c);
-            if space_size == 0 {
-                return 0;
-            } else {
-                iter.next();
-                return space_size + consume_space(iter);
-            }
+/// Skips the spaces at the start of `text`, returning their size and the rest of the text
+fn consume_space(text: &str) -> (usize, &str) {
+    let mut size = 0;
+    for (i, c) in text.char_indices() {
+        match whitespace_size(c) {
+            0 => return (size, &text[i..]),
+            space_size => size += space_size,
         }
     }
+    (size, "")
 }
 
-fn whitespace_size(c: &char) -> u8 {
+fn whitespace_size(c: char) -> usize {
     match c {
-        &' ' => 1,
-        &'\t' => 2,
+        ' ' => 1,
+        '\t' => 2,
         _ => 0,
     }
 }
 
-fn is_comment_indicator(c: &char) -> bool {
-    c == &';' || c == &'#'
+fn is_comment_indicator(c: char) -> bool {
+    c == ';' || c == '#'
 }
 
-fn is_status(c: &char) -> bool {
-    c == &'!' || c == &'*'
+fn is_status(c: char) -> bool {
+    c == '!' || c == '*'
 }
 
 #[cfg(test)]
@@ -486,12 +957,9 @@ mod tests {
     fn it_parses_quantities() {
         let inputs = vec!["-489.61", "-10", "423.03", "21.25", "15.03", "40.30", "5.6"];
         let expected = vec![-48961, -1000, 42303, 2125, 1503, 4030, 560];
-        for (input, expected) in inputs.into_iter().zip(expected.into_iter()) {
-            // let input = "423.01".to_owned();
-            let output = parse_quantity(input.to_owned());
-            // let output = parse_quantity(input.to_owned());
-            assert!(output.is_ok());
-            assert_eq!(output.unwrap(), expected)
+        for (input, expected) in inputs.into_iter().zip(expected) {
+            let output = parse_quantity(input);
+            assert_eq!(output, Some(expected))
         }
     }
 }
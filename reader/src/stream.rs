@@ -0,0 +1,360 @@
+//! Reading journals one transaction at a time.
+//!
+//! `Reader` collects everything it reads before returning it, which suits reports that sort or cross-reference
+//! transactions. A `Stream` instead yields each transaction as soon as it has been parsed, with any errors in the
+//! order they were found. Only directives such as payees and prices are kept, and files are read a line at a time,
+//! so processing a journal this way takes memory that doesn't grow with its number of transactions.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::mpsc::Receiver,
+    time::{Duration, Instant},
+};
+
+use journal::{Payee, PeriodicTransaction, Posting, Price, Transaction};
+use regex::{Regex, RegexBuilder};
+
+use super::{
+    error::{Error, ErrorKind},
+    reader::STDIN,
+    source::{ItemKind, ParsedItem, Reading, Source},
+};
+
+/// A transaction and its postings
+/// The postings are in the order they were written and the indexes linking them are left unset
+pub type Entry = (Transaction, Vec<Posting>);
+
+/// Yields the transactions in journals in the order they were written, merging included files in place
+pub struct Stream {
+    /// The sources still being read, with the one to read next on top
+    receivers: Vec<Receiver<Result<ParsedItem, Error>>>,
+    /// Roots which won't be read because they were passed more than once
+    pending_errors: Vec<Error>,
+    /// The same file can be reached through different paths so we compare canonical paths
+    canonical_sources: HashSet<PathBuf>,
+    sources: HashSet<PathBuf>,
+    transaction_counts: HashMap<PathBuf, usize>,
+    payees: Vec<Payee>,
+    prices: Vec<Price>,
+    periodic_transactions: Vec<PeriodicTransaction>,
+    /// The patterns of the payee aliases read so far and the payee each renames to
+    aliases: Vec<(Regex, String)>,
+    /// Whether to rename payees as they are yielded
+    rename_payees: bool,
+    /// When the sources started being read
+    opened: Instant,
+}
+
+impl Stream {
+    /// Starts reading the journals at `locations`, which may be `STDIN`
+    /// Without `recover` nothing more is read from a file after its first error
+    pub fn open<I, P>(locations: I, recover: bool) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self::open_with(locations, recover, Reading::Lines)
+    }
+
+    /// Starts reading the journals at `locations` like `open`, reading each file as `reading` says
+    pub(crate) fn open_with<I, P>(locations: I, recover: bool, reading: Reading) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        let opened = Instant::now();
+
+        // Start parsing every file straight away so they are read in parallel
+        let roots = locations
+            .into_iter()
+            .map(|location| {
+                let location = location.into();
+                if location == Path::new(STDIN) {
+                    let location = PathBuf::from("<stdin>");
+                    let receiver = Source::spawn_reader(location.clone(), io::stdin(), recover, reading);
+                    return (location, receiver);
+                }
+
+                let receiver = Source::spawn(location.clone(), recover, reading);
+                (location, receiver)
+            })
+            .collect();
+
+        Self::from_receivers(roots, opened)
+    }
+
+    /// Starts reading a journal from `contents`, naming it `location` in errors
+    /// Included files are resolved relative to the current directory
+    pub fn from_reader<P, R>(location: P, contents: R, recover: bool) -> Self
+    where
+        P: Into<PathBuf>,
+        R: Read + Send + 'static,
+    {
+        Self::from_reader_with(location, contents, recover, Reading::Lines)
+    }
+
+    /// Starts reading a journal from `contents` like `from_reader`, reading it as `reading` says
+    pub(crate) fn from_reader_with<P, R>(location: P, contents: R, recover: bool, reading: Reading) -> Self
+    where
+        P: Into<PathBuf>,
+        R: Read + Send + 'static,
+    {
+        let opened = Instant::now();
+        let location = location.into();
+        let receiver = Source::spawn_reader(location.clone(), contents, recover, reading);
+        Self::from_receivers(vec![(location, receiver)], opened)
+    }
+
+    fn from_receivers(roots: Vec<(PathBuf, Receiver<Result<ParsedItem, Error>>)>, opened: Instant) -> Self {
+        let mut stream = Self {
+            receivers: Vec::with_capacity(roots.len()),
+            pending_errors: Vec::new(),
+            canonical_sources: HashSet::new(),
+            sources: HashSet::new(),
+            transaction_counts: HashMap::new(),
+            payees: Vec::new(),
+            prices: Vec::new(),
+            periodic_transactions: Vec::new(),
+            aliases: Vec::new(),
+            rename_payees: true,
+            opened,
+        };
+
+        for (location, receiver) in roots {
+            if !stream.canonical_sources.insert(canonicalize(&location)) {
+                let error = Error::new(ErrorKind::DuplicateSource(location.clone()), location, 0);
+                stream.pending_errors.push(error);
+                continue;
+            }
+            stream.sources.insert(location);
+            stream.receivers.push(receiver);
+        }
+        // The receivers are a stack so the roots go on in reverse, as do their errors
+        stream.receivers.reverse();
+        stream.pending_errors.reverse();
+
+        stream
+    }
+
+    /// Leaves payees as they were written rather than applying the aliases read so far
+    /// Aliases can come after the transactions they apply to, so `alias_of` gives the name once everything is read
+    pub fn without_renaming(mut self) -> Self {
+        self.rename_payees = false;
+        self
+    }
+
+    /// The name the first alias read so far that matches `payee` renames it to, if any match
+    pub fn alias_of(&self, payee: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(alias, _)| alias.is_match(payee))
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The time since the stream started reading, which includes any time spent on what it has yielded
+    pub fn elapsed(&self) -> Duration {
+        self.opened.elapsed()
+    }
+
+    /// The paths of the files read so far, including included files
+    pub fn sources(&self) -> &HashSet<PathBuf> {
+        &self.sources
+    }
+
+    /// How many transactions have been read from each source, leaving out sources with none
+    pub fn transaction_counts(&self) -> &HashMap<PathBuf, usize> {
+        &self.transaction_counts
+    }
+
+    /// The payees declared with payee directives so far
+    pub fn payees(&self) -> &[Payee] {
+        &self.payees
+    }
+
+    /// The prices declared with price directives so far
+    pub fn prices(&self) -> &[Price] {
+        &self.prices
+    }
+
+    /// The transactions written with a `~` header so far, which aren't yielded with the others
+    pub fn periodic_transactions(&self) -> &[PeriodicTransaction] {
+        &self.periodic_transactions
+    }
+
+    /// Records the items that aren't transactions, returning the transactions
+    fn handle(&mut self, item: ParsedItem) -> Option<Result<Entry, Error>> {
+        match item.kind {
+            ItemKind::Transaction(mut transaction, postings) => {
+                *self.transaction_counts.entry(item.location).or_insert(0) += 1;
+
+                if self.rename_payees {
+                    if let Some(name) = self.alias_of(&transaction.payee) {
+                        transaction.payee = name.to_owned();
+                    }
+                }
+
+                Some(Ok((transaction, postings)))
+            }
+            ItemKind::IncludedSource(include, receiver) => {
+                if !self.canonical_sources.insert(canonicalize(&include)) {
+                    // Dropping the receiver stops the duplicate source
+                    let error = Error::new(ErrorKind::DuplicateSource(include), item.location, item.line);
+                    return Some(Err(error));
+                }
+                self.sources.insert(include);
+                self.receivers.push(receiver);
+                None
+            }
+            ItemKind::PayeeDirective(payee) => {
+                // The patterns were checked when they were parsed
+                for alias in &payee.aliases {
+                    if let Ok(regex) = RegexBuilder::new(alias).case_insensitive(true).build() {
+                        self.aliases.push((regex, payee.name.clone()));
+                    }
+                }
+                self.payees.push(payee);
+                None
+            }
+            ItemKind::PriceDirective(price) => {
+                self.prices.push(price);
+                None
+            }
+            ItemKind::PeriodicTransaction(periodic_transaction) => {
+                self.periodic_transactions.push(periodic_transaction);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for Stream {
+    type Item = Result<Entry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Roots which were passed twice are reported before anything is read
+            if let Some(error) = self.pending_errors.pop() {
+                return Some(Err(error));
+            }
+
+            // Included sources are drained at the point they were included so that parse order matches file
+            // order regardless of thread scheduling
+            let item = match self.receivers.last()?.recv() {
+                Ok(Ok(item)) => item,
+                Ok(Err(e)) => return Some(Err(e)),
+                Err(_) => {
+                    // The source has finished
+                    self.receivers.pop();
+                    continue;
+                }
+            };
+
+            if let Some(entry) = self.handle(item) {
+                return Some(entry);
+            }
+        }
+    }
+}
+
+/// Falls back to the path as given if it can't be canonicalised (e.g. it isn't a file)
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests").join(name)
+    }
+
+    fn stream_str(contents: &str) -> Stream {
+        Stream::from_reader("<string>", io::Cursor::new(contents.to_owned()), true)
+    }
+
+    #[test]
+    fn it_yields_transactions_in_file_order() {
+        let payees: Vec<String> = Stream::open(vec![journal("test.journal")], false)
+            .map(|entry| entry.unwrap().0.payee)
+            .collect();
+        assert_eq!(
+            payees,
+            vec![
+                "A restaurant",
+                "Clothes store",
+                "Jéby̆",
+                "Grocery store",
+                "A restaurant",
+                "clothes store",
+                "Cash"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_interleaves_errors_with_transactions() {
+        let lines: Vec<Result<String, u64>> = Stream::open(vec![journal("errors.journal")], true)
+            .map(|entry| entry.map(|(t, _)| t.payee).map_err(|e| e.line))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                Ok("Salary".to_owned()),
+                Err(8),
+                Err(14),
+                Ok("Cash".to_owned()),
+                Err(23),
+                Err(28),
+                Err(32),
+                Ok("Book shop".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_applies_aliases_read_so_far() {
+        let mut stream = stream_str(
+            "2020-01-01 AMZN Mktp UK
+    Expenses:Shopping    £10
+    Assets:Current
+
+payee Amazon
+    alias ^amzn
+
+2020-01-02 AMZN Mktp UK
+    Expenses:Shopping    £5
+    Assets:Current
+",
+        );
+
+        assert_eq!(stream.next().unwrap().unwrap().0.payee, "AMZN Mktp UK");
+        assert!(stream.payees().is_empty());
+        assert_eq!(stream.next().unwrap().unwrap().0.payee, "Amazon");
+        assert_eq!(stream.payees().len(), 1);
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn it_reports_invalid_utf8_on_its_line() {
+        let contents = b"2020-01-01 Shop\n    Expenses:Shopping    \xa310\n    Assets:Current\n".to_vec();
+        let mut stream = Stream::from_reader("<bytes>", io::Cursor::new(contents), true);
+
+        let error = stream.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::IO(_)));
+        assert_eq!(error.line, 2);
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn it_reports_files_read_twice() {
+        let location = journal("included.journal");
+        let results: Vec<bool> = Stream::open(vec![location.clone(), location], false)
+            .map(|entry| entry.is_ok())
+            .collect();
+        assert_eq!(results, vec![false, true, true]);
+    }
+}
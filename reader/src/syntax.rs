@@ -0,0 +1,338 @@
+//! A concrete syntax tree for journal files.
+//!
+//! Unlike the `Source` parser, which only keeps what reports need, every line here keeps its original text
+//! and line ending so a file can be re-emitted byte-for-byte. Each line is also broken into its parts so it
+//! can be re-emitted in a normalised form.
+
+use std::path::{Path, PathBuf};
+
+use super::{
+    error::{Error, ErrorKind},
+    include,
+};
+
+/// How indented lines are indented when formatting
+const INDENT: &str = "    ";
+
+/// A parsed journal file
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct File {
+    pub lines: Vec<Line>,
+}
+
+/// A single line of a journal file
+#[derive(Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The line as it was read, without its ending
+    pub text: String,
+    /// The line ending as it was read: "\n", "\r\n" or "" for a final line without one
+    pub ending: String,
+    pub kind: LineKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    /// A comment at the start of a line, outside of a transaction
+    FileComment,
+    /// The argument of an include directive
+    IncludeDirective(String),
+    TransactionHeader,
+    PeriodicTransactionHeader,
+    /// An indented comment belonging to a transaction or posting
+    Comment {
+        indicator: char,
+        comment: String,
+    },
+    Posting(PostingSyntax),
+    /// Anything else, which is passed through untouched
+    Unknown,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PostingSyntax {
+    pub account: String,
+    /// The amount as written, if there is one
+    pub amount: Option<String>,
+    /// The indicator and text of a comment following the amount on the same line
+    pub comment: Option<(char, String)>,
+}
+
+pub struct FormatOptions {
+    /// The column that posting amounts are right-aligned to
+    pub amount_column: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self { amount_column: 52 }
+    }
+}
+
+impl File {
+    /// Reads and parses the file at `location`
+    pub fn read(location: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(location).map_err(|e| Error::new(ErrorKind::IO(e), location, 0))?;
+        Ok(Self::parse(&contents))
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut rest = contents;
+
+        while !rest.is_empty() {
+            let (text, ending, remaining) = match rest.find('\n') {
+                None => (rest, "", ""),
+                Some(i) if i > 0 && rest.as_bytes()[i - 1] == b'\r' => (&rest[..i - 1], "\r\n", &rest[i + 1..]),
+                Some(i) => (&rest[..i], "\n", &rest[i + 1..]),
+            };
+
+            lines.push(Line {
+                text: text.to_owned(),
+                ending: ending.to_owned(),
+                kind: LineKind::parse(text),
+            });
+            rest = remaining;
+        }
+
+        Self { lines }
+    }
+
+    /// Re-emits the file in a normalised form: indented lines use four spaces, amounts are aligned to
+    /// `options.amount_column`, trailing whitespace is removed and lines end with "\n"
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let mut output = String::with_capacity(self.lines.iter().map(|l| l.text.len() + 1).sum());
+
+        for line in &self.lines {
+            line.format_into(&mut output, options);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Resolves the files included by this file, which is at `location`
+    pub fn includes(&self, location: &Path) -> Result<Vec<PathBuf>, Error> {
+        let parent = location.parent().unwrap_or_else(|| Path::new(""));
+        let mut includes = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if let LineKind::IncludeDirective(path) = &line.kind {
+                let mut paths = include::resolve(path, parent, location)
+                    .map_err(|kind| Error::new(kind, location, i as u64 + 1).with_text(&line.text))?;
+                includes.append(&mut paths);
+            }
+        }
+
+        Ok(includes)
+    }
+}
+
+impl std::fmt::Display for File {
+    /// Writes the file exactly as it was parsed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}{}", line.text, line.ending)?;
+        }
+        Ok(())
+    }
+}
+
+impl Line {
+    fn format_into(&self, output: &mut String, options: &FormatOptions) {
+        match &self.kind {
+            LineKind::Blank => {}
+            LineKind::IncludeDirective(path) => {
+                output.push_str("include ");
+                output.push_str(path.trim());
+            }
+            LineKind::Comment { indicator, comment } => {
+                output.push_str(INDENT);
+                output.push(*indicator);
+                if !comment.is_empty() {
+                    output.push(' ');
+                    output.push_str(comment);
+                }
+            }
+            LineKind::Posting(posting) => posting.format_into(output, options),
+            LineKind::FileComment
+            | LineKind::TransactionHeader
+            | LineKind::PeriodicTransactionHeader
+            | LineKind::Unknown => output.push_str(self.text.trim_end()),
+        }
+    }
+}
+
+impl LineKind {
+    fn parse(text: &str) -> Self {
+        let first = match text.chars().next() {
+            None => return LineKind::Blank,
+            Some(c) => c,
+        };
+
+        if text.trim().is_empty() {
+            return LineKind::Blank;
+        }
+
+        match first {
+            c if c.is_numeric() => LineKind::TransactionHeader,
+            '~' => LineKind::PeriodicTransactionHeader,
+            ';' | '#' | '%' | '|' | '*' => LineKind::FileComment,
+            c if c.is_whitespace() => {
+                let trimmed = text.trim();
+                match trimmed.chars().next() {
+                    Some(indicator) if indicator == ';' || indicator == '#' => LineKind::Comment {
+                        indicator,
+                        comment: trimmed[1..].trim().to_owned(),
+                    },
+                    _ => LineKind::Posting(PostingSyntax::parse(trimmed)),
+                }
+            }
+            _ => match text.strip_prefix("include") {
+                Some(path) if path.starts_with(char::is_whitespace) => {
+                    LineKind::IncludeDirective(path.trim().to_owned())
+                }
+                _ => LineKind::Unknown,
+            },
+        }
+    }
+}
+
+impl PostingSyntax {
+    /// Parses a posting with its indentation removed
+    fn parse(text: &str) -> Self {
+        // Accounts end at a tab or two spaces, in the same way as the Source parser
+        let account_end = text
+            .char_indices()
+            .find(|(i, c)| *c == '\t' || text[*i..].starts_with("  "))
+            .map(|(i, _)| i)
+            .unwrap_or(text.len());
+
+        let (account, rest) = text.split_at(account_end);
+
+        // Either indicator starts a comment, as it does for a comment on its own line
+        let (amount, comment) = match rest.find(&[';', '#'][..]) {
+            None => (rest, None),
+            Some(i) => {
+                let indicator = rest[i..].chars().next().unwrap();
+                (&rest[..i], Some((indicator, rest[i + 1..].trim().to_owned())))
+            }
+        };
+
+        let amount = amount.trim();
+
+        Self {
+            account: account.trim_end().to_owned(),
+            amount: if amount.is_empty() {
+                None
+            } else {
+                Some(amount.to_owned())
+            },
+            comment,
+        }
+    }
+
+    fn format_into(&self, output: &mut String, options: &FormatOptions) {
+        output.push_str(INDENT);
+        output.push_str(&self.account);
+
+        if let Some(amount) = &self.amount {
+            let used = INDENT.len() + self.account.chars().count() + amount.chars().count();
+            let padding = options.amount_column.saturating_sub(used).max(2);
+            output.push_str(&" ".repeat(padding));
+            output.push_str(amount);
+        }
+
+        if let Some((indicator, comment)) = &self.comment {
+            output.push_str("  ");
+            output.push(*indicator);
+            if !comment.is_empty() {
+                output.push(' ');
+                output.push_str(comment);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tests_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests")
+    }
+
+    #[test]
+    fn it_round_trips_files() {
+        for name in &[
+            "test.journal",
+            "included.journal",
+            "context.journal",
+            "budget-test.journal",
+        ] {
+            let contents = std::fs::read_to_string(tests_dir().join(name)).unwrap();
+            assert_eq!(File::parse(&contents).to_string(), contents);
+        }
+
+        let contents = "2020-01-01 Shop  \r\n\t  Assets:Cash\t£1 ;x\r\n  Income";
+        assert_eq!(File::parse(contents).to_string(), contents);
+    }
+
+    #[test]
+    fn it_keeps_hash_comments_on_postings() {
+        let contents = "2020-01-01 Shop\n    Expenses:Food  £1  # lunch\n    Assets:Cash  #\n";
+        let file = File::parse(contents);
+        assert_eq!(file.to_string(), contents);
+
+        let expected = PostingSyntax {
+            account: "Expenses:Food".to_owned(),
+            amount: Some("£1".to_owned()),
+            comment: Some(('#', "lunch".to_owned())),
+        };
+        assert_eq!(file.lines[1].kind, LineKind::Posting(expected));
+
+        let options = FormatOptions { amount_column: 24 };
+        let expected = "2020-01-01 Shop\n    Expenses:Food     £1  # lunch\n    Assets:Cash  #\n";
+        assert_eq!(file.format(&options), expected);
+        assert_eq!(File::parse(expected).format(&options), expected);
+    }
+
+    #[test]
+    fn it_parses_postings() {
+        let file = File::parse("2020-01-01 Shop\n  Expenses:Eating out  £10.00  ; lunch\n\tAssets:Cash\n");
+
+        let expected = PostingSyntax {
+            account: "Expenses:Eating out".to_owned(),
+            amount: Some("£10.00".to_owned()),
+            comment: Some((';', "lunch".to_owned())),
+        };
+        assert_eq!(file.lines[1].kind, LineKind::Posting(expected));
+
+        let expected = PostingSyntax {
+            account: "Assets:Cash".to_owned(),
+            amount: None,
+            comment: None,
+        };
+        assert_eq!(file.lines[2].kind, LineKind::Posting(expected));
+    }
+
+    #[test]
+    fn it_formats_files() {
+        let contents = "include  other.journal \n\n2020-01-02 * Grocery store  ; Weekly shop \n\t\t; transaction note\n\t\tAssets:Cash  £-40\r\n    Expenses:Groceries    £40\n  Assets:Current\n;comment   ";
+        let expected = "include other.journal
+
+2020-01-02 * Grocery store  ; Weekly shop
+    ; transaction note
+    Assets:Cash                 £-40
+    Expenses:Groceries           £40
+    Assets:Current
+;comment
+";
+        let options = FormatOptions { amount_column: 36 };
+        let formatted = File::parse(contents).format(&options);
+        assert_eq!(formatted, expected);
+
+        // Formatting is idempotent
+        assert_eq!(File::parse(&formatted).format(&options), expected);
+    }
+}
@@ -1,35 +1,48 @@
-use journal::Posting;
 use reader::error::Error;
-use reader::reader::{Config, Reader};
+use reader::stream::Stream;
 use tree::Tree;
 
-pub struct Accounts<'a> {
-    tree: Tree<'a, usize>,
-    postings: Vec<Posting>,
-}
+use crate::command::{Report, StreamCommand};
+use crate::output::{print_csv, print_json, OutputFormat};
 
-impl<'a> Accounts<'a> {
-    pub fn new() -> Self {
-        Self {
-            tree: Tree::new(),
-            postings: Vec::new(),
-        }
-    }
+/// Every account with a posting, along with its parents
+pub struct Accounts;
 
-    pub fn read(&'a mut self, file: String) -> Result<(), Error> {
-        let mut reader = Reader::new();
-        let config = Config::new();
+pub struct AccountsReport {
+    tree: Tree<()>,
+}
 
-        let (_, postings, _) = reader.read(file, config)?;
-        self.postings = postings;
+impl StreamCommand for Accounts {
+    type Report = AccountsReport;
 
-        for posting in &self.postings {
-            let mut path: Vec<&str> = posting.path.split(':').collect();
-            self.tree.add_path(&mut path);
+    fn run(&self, stream: Stream) -> Result<Self::Report, Error> {
+        let mut tree = Tree::new();
+        for entry in stream {
+            let (_, postings) = entry?;
+            for posting in &postings {
+                let path: Vec<&str> = posting.path.split(':').collect();
+                tree.add_path(&path);
+            }
         }
 
-        self.tree.display(&None, |_| None);
+        Ok(AccountsReport { tree })
+    }
+}
+
+impl Report for AccountsReport {
+    fn print(&self, format: OutputFormat) {
+        // Machine-readable formats list the full name of every account, parents before their children
+        let mut names = Vec::new();
+        if format != OutputFormat::Text {
+            self.tree.walk(|path, _| names.push(path.join(":")));
+        }
 
-        Ok(())
+        match format {
+            OutputFormat::Text => self.tree.display(&None, |_| None),
+            // The subcommand only accepts formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("accounts has no HTML output"),
+            OutputFormat::Json => print_json(&names),
+            OutputFormat::Csv => print_csv(&["account"], names.into_iter().map(|name| [name])),
+        }
     }
 }
@@ -1,57 +1,771 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
 use journal::Amount;
-use journal::Posting;
-use reader::reader::{Config, Reader};
-use tree::Tree;
+use journal::Journal;
+use serde::{Deserialize, Serialize};
+use tree::{Diff, Guides, Node, Renderer, Tree};
+
+use crate::command::{Command, Report};
+use crate::html;
+use crate::output::{print_csv, print_json, OutputFormat};
+
+/// The balance of an account, with one amount for each commodity posted to it
+/// Serialises as its amounts
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Account {
+    /// Ordered by commodity
+    amounts: Vec<Amount>,
+}
+
+impl Account {
+    fn add(&mut self, amount: &Amount) {
+        match self.amounts.binary_search_by(|a| a.commodity.cmp(&amount.commodity)) {
+            Ok(i) => self.amounts[i].quantity += amount.quantity,
+            Err(i) => self.amounts.insert(i, amount.clone()),
+        }
+    }
+
+    fn add_account(&mut self, account: &Account) {
+        for amount in &account.amounts {
+            self.add(amount);
+        }
+    }
+
+    /// The amounts which aren't zero
+    /// If there are none, this is the zero amounts, or a single zero amount if nothing was posted
+    fn balance(&self) -> Vec<&Amount> {
+        let balance: Vec<&Amount> = self.amounts.iter().filter(|a| a.quantity != 0).collect();
+        match (balance.is_empty(), self.amounts.is_empty()) {
+            (false, _) => balance,
+            (true, false) => self.amounts.iter().collect(),
+            (true, true) => vec![&ZERO],
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.amounts.iter().all(|a| a.quantity == 0)
+    }
+
+    /// The quantity of `commodity`, which is zero if it wasn't posted
+    fn quantity(&self, commodity: &str) -> i64 {
+        match self.amounts.binary_search_by(|a| a.commodity.as_str().cmp(commodity)) {
+            Ok(i) => self.amounts[i].quantity,
+            Err(_) => 0,
+        }
+    }
+
+    /// Orders balances by their quantities, taking commodities in turn
+    fn cmp_quantities(&self, other: &Self) -> Ordering {
+        let quantities = |account: &Self| account.balance().into_iter().map(|a| a.quantity).collect::<Vec<_>>();
+        quantities(self).cmp(&quantities(other))
+    }
+}
+
+/// Accounts are equal if their balances are, whichever commodities they once had
+impl PartialEq for Account {
+    fn eq(&self, other: &Self) -> bool {
+        self.balance() == other.balance()
+    }
+}
+
+/// The balance of an account with no postings
+static ZERO: Amount = Amount {
+    commodity: String::new(),
+    quantity: 0,
+};
+
+/// Balances saved with `balance -O json` or `balance -O csv`, for comparing the journal with
+pub struct Snapshot(Tree<Account>);
+
+/// A row of a snapshot saved as CSV; other columns are ignored
+#[derive(Deserialize)]
+struct SnapshotRecord {
+    account: String,
+    commodity: String,
+    quantity: String,
+}
+
+impl Snapshot {
+    /// Reads a snapshot as CSV if its name ends in .csv, or as JSON otherwise
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let error = |e: String| format!("failed to read {}: {}", path.display(), e);
+
+        let file = File::open(path).map_err(|e| error(e.to_string()))?;
+        let tree = match path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+            true => Self::read_csv(file),
+            false => serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string()),
+        };
+
+        tree.map(Snapshot).map_err(error)
+    }
+
+    fn read_csv<R: Read>(reader: R) -> Result<Tree<Account>, String> {
+        let mut tree: Tree<Account> = Tree::new();
+        let mut recorded = HashSet::new();
+
+        for record in csv::Reader::from_reader(reader).deserialize() {
+            let record: SnapshotRecord = record.map_err(|e| e.to_string())?;
+            let quantity = Amount::parse_quantity(&record.quantity)
+                .ok_or_else(|| format!("invalid quantity: {}", record.quantity))?;
+
+            // Accounts with several commodities have a row for each
+            let path: Vec<&str> = record.account.split(':').collect();
+            let index = tree.add_path(&path);
+            let amount = Amount::new(quantity, &record.commodity);
+            tree.get_node_at_index_mut(index).unwrap().value.add(&amount);
+            recorded.insert(index);
+        }
+
+        // Parents shown on the same line as their only child have no row, so their balance is their children's
+        let unrecorded: Vec<usize> = tree
+            .post_order()
+            .map(|(index, _, _, _)| index)
+            .filter(|index| !recorded.contains(index))
+            .collect();
+        for index in unrecorded {
+            let mut total = Account::default();
+            for (_, child) in tree.children(index) {
+                total.add_account(&tree.get_node_at_index(child).unwrap().value);
+            }
+            tree.get_node_at_index_mut(index).unwrap().value = total;
+        }
+
+        Ok(tree)
+    }
+}
+
+/// The order sibling accounts are shown in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    /// Smallest balance first
+    Amount,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    /// Accounts deeper than this are collapsed into their ancestor at this depth
+    pub depth: Option<usize>,
+    /// Show full account names on one line each instead of a hierarchy
+    pub flat: bool,
+    /// Show accounts with a zero balance
+    pub empty: bool,
+    pub sort: SortKey,
+    /// Show a separator and the total of every account after the accounts
+    pub total: bool,
+    /// Show each account's balance as a percentage of its parent's instead of an amount
+    /// The total is still shown as an amount
+    pub percent: bool,
+}
+
+/// An account as it is shown in the report
+struct Row<'r> {
+    /// The full name of the account
+    path: String,
+    /// The name shown for the account, which includes any parents elided into it
+    name: String,
+    /// How many shown ancestors the account has
+    indent: usize,
+    amount: &'r Account,
+    /// The balance of the account's parent, or None for top-level accounts
+    parent_amount: Option<&'r Account>,
+    /// Whether the next row is this account's child
+    has_children: bool,
+}
+
+/// A row placed in the tree that is rendered as text
+#[derive(Default)]
+struct ShownRow {
+    /// The row's position in the report, which siblings are rendered in
+    order: usize,
+    cell: String,
+}
+
+pub struct Balance {
+    options: Options,
+    /// The balances to compare the journal's with
+    snapshot: Option<Snapshot>,
+}
+
+pub enum BalanceReport {
+    Balances(Balances),
+    /// The snapshot's balances and the journal's, leaving out those the options hide
+    Comparison(Tree<Diff<Account>>),
+}
+
+pub struct Balances {
+    /// Every account
+    tree: Tree<Account>,
+    /// The accounts which are shown
+    shown: Tree<Account>,
+    /// The sum of every posting in each commodity
+    total: Account,
+    options: Options,
+}
+
+impl Balance {
+    pub fn new(options: Options, snapshot: Option<Snapshot>) -> Self {
+        Self { options, snapshot }
+    }
+
+    /// Removes the accounts which shouldn't be shown
+    fn prune(&self, tree: &mut Tree<Account>) {
+        // An account with a zero balance is kept if any of its descendants has a balance
+        if !self.options.empty {
+            tree.prune(|_, node| !node.value.is_zero());
+        }
+
+        if let Some(depth) = self.options.depth {
+            tree.prune(|path, _| path.len() <= depth);
+        }
+
+        tree.compact();
+    }
+}
+
+impl Command for Balance {
+    type Report<'j> = BalanceReport;
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        let mut tree: Tree<Account> = Tree::new();
+        for posting in journal.postings() {
+            let path: Vec<&str> = posting.path.split(':').collect();
+            let index = tree.add_path(&path);
+
+            let amount = posting.amount.as_ref().unwrap();
+            tree.get_node_at_index_mut(index).unwrap().value.add(amount);
+        }
+        tree.aggregate(|total, account| total.add_account(account));
 
-struct Account {
-    amount: Amount,
+        if let Some(snapshot) = &self.snapshot {
+            let mut diff = snapshot.0.diff(&tree);
+            if !self.options.empty {
+                diff.prune(|_, node| has_changed(node));
+            }
+            if let Some(depth) = self.options.depth {
+                diff.prune(|path, _| path.len() <= depth);
+            }
+            return BalanceReport::Comparison(diff);
+        }
+
+        let mut total = Account::default();
+        for amount in journal.postings().iter().filter_map(|p| p.amount.as_ref()) {
+            total.add(amount);
+        }
+
+        let mut shown = tree.clone();
+        self.prune(&mut shown);
+
+        BalanceReport::Balances(Balances {
+            tree,
+            shown,
+            total,
+            options: self.options,
+        })
+    }
 }
 
-impl Default for Account {
-    fn default() -> Account {
-        Account {
-            amount: Amount::new(0, ""),
+impl Report for BalanceReport {
+    fn print(&self, format: OutputFormat) {
+        match self {
+            BalanceReport::Balances(balances) => balances.print(format),
+            BalanceReport::Comparison(diff) => print_comparison(diff, format),
+        }
+    }
+}
+
+impl Balances {
+    fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => {
+                let stdout = io::stdout();
+                self.write_text(&mut stdout.lock()).expect("failed to write to stdout");
+            }
+            // JSON is the whole tree, so consumers can apply their own depth and filtering
+            OutputFormat::Json => print_json(&self.tree),
+            OutputFormat::Csv => {
+                let mut header = vec!["account", "commodity", "quantity"];
+                if self.options.percent {
+                    header.push("percent");
+                }
+
+                // An account has a record for each commodity
+                let rows = self.rows();
+                let records = rows.iter().flat_map(|row| {
+                    let percents = percents(row);
+                    row.amount
+                        .balance()
+                        .into_iter()
+                        .zip(percents)
+                        .map(move |(amount, percent)| {
+                            let mut record = vec![row.path.clone(), amount.commodity.clone(), amount.quantity_string()];
+                            if self.options.percent {
+                                record.push(percent.trim_end_matches('%').to_owned());
+                            }
+                            record
+                        })
+                });
+                print_csv(&header, records)
+            }
+            OutputFormat::Html => {
+                let rows = self.rows();
+                let rows: Vec<html::TreeRow> = rows
+                    .iter()
+                    .map(|row| html::TreeRow {
+                        name: &row.name,
+                        depth: row.indent,
+                        amounts: row.amount.balance(),
+                        has_children: row.has_children,
+                    })
+                    .collect();
+                let mut body = html::tree(&rows);
+                if self.options.total {
+                    body.push_str(&html::total(&self.total.balance()));
+                }
+                print!("{}", html::document("Balance", &body));
+            }
+        }
+    }
+
+    /// Writes the accounts as an indented tree, followed by the total
+    fn write_text<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        // The rows go in a tree of their own so that collapsed parents and flat names are single nodes
+        let rows = self.rows();
+        let mut shown: Tree<ShownRow> = Tree::new();
+        let mut path: Vec<&str> = Vec::new();
+        for (order, row) in rows.iter().enumerate() {
+            path.truncate(row.indent);
+            path.push(&row.name);
+            let cell = match self.options.percent {
+                true => percents(row).join("\n"),
+                false => lines(&row.amount.balance()),
+            };
+            shown.add_value_at_path(&path, ShownRow { order, cell });
+        }
+
+        let mut renderer = Renderer::new(Guides::Indent)
+            .column("", |node: &Node<ShownRow>| node.value.cell.clone())
+            .sort_by(|a, b| a.value.order.cmp(&b.value.order));
+        if self.options.total {
+            renderer = renderer.footer(vec![lines(&self.total.balance())]);
+        }
+
+        renderer.render(&shown, shown.root(), w)
+    }
+
+    /// The accounts to show, in order
+    fn rows(&self) -> Vec<Row<'_>> {
+        let mut rows = Vec::new();
+        self.add_rows(self.shown.root(), &[], 0, &mut rows);
+
+        if self.options.flat {
+            // Only the accounts at the bottom of the hierarchy are shown, with their full names
+            let mut rows: Vec<Row> = rows
+                .into_iter()
+                .filter(|row| !row.has_children)
+                .map(|row| Row {
+                    name: row.path.clone(),
+                    indent: 0,
+                    ..row
+                })
+                .collect();
+            if self.options.sort == SortKey::Amount {
+                rows.sort_by(|a, b| a.amount.cmp_quantities(b.amount));
+            }
+            return rows;
         }
+
+        rows
+    }
+
+    /// Adds rows for the children of the node at `index`, whose full name is `path`
+    fn add_rows<'r>(&'r self, index: usize, path: &[&'r str], indent: usize, rows: &mut Vec<Row<'r>>) {
+        let mut children = self.shown.children(index);
+        if self.options.sort == SortKey::Amount {
+            children.sort_by(|(_, a), (_, b)| self.amount(*a).cmp_quantities(self.amount(*b)));
+        }
+
+        for (name, mut child) in children {
+            let mut path = path.to_vec();
+            path.push(name);
+            let mut names = vec![name];
+
+            // A parent with a single child and no postings of its own is shown on the same line, as ledger does
+            if !self.options.flat {
+                loop {
+                    let grandchildren = self.shown.children(child);
+                    match grandchildren.as_slice() {
+                        [(name, grandchild)] if self.amount(*grandchild) == self.amount(child) => {
+                            path.push(name);
+                            names.push(name);
+                            child = *grandchild;
+                        }
+                        _ => break,
+                    }
+                }
+            }
+
+            let row_index = rows.len();
+            rows.push(Row {
+                path: path.join(":"),
+                name: names.join(":"),
+                indent,
+                amount: self.amount(child),
+                parent_amount: match index == self.shown.root() {
+                    true => None,
+                    false => Some(self.amount(index)),
+                },
+                has_children: false,
+            });
+
+            self.add_rows(child, &path, indent + 1, rows);
+            rows[row_index].has_children = rows.len() > row_index + 1;
+        }
+    }
+
+    fn amount(&self, index: usize) -> &Account {
+        &self.shown.get_node_at_index(index).unwrap().value
+    }
+}
+
+/// Each amount in the row's balance as a percentage of its parent's balance in the same commodity
+/// Top-level accounts are 100% and amounts whose commodity has a zero balance in the parent have no percentage
+fn percents(row: &Row) -> Vec<String> {
+    let percent = |amount: &Amount| match row.parent_amount.map(|p| p.quantity(&amount.commodity)) {
+        None => "100.00%".to_owned(),
+        Some(0) => "-".to_owned(),
+        Some(parent) => format!("{:.2}%", amount.quantity as f64 / parent as f64 * 100.),
+    };
+    row.amount.balance().into_iter().map(percent).collect()
+}
+
+/// Amounts as text, one per line
+/// A zero balance is shown as 0 whatever its commodities, as ledger does
+fn lines(amounts: &[&Amount]) -> String {
+    if amounts.iter().all(|a| a.quantity == 0) {
+        return "0".to_owned();
     }
+
+    amounts.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("\n")
 }
 
-pub struct Balance<'a> {
-    tree: Tree<'a, Account>,
-    postings: Vec<Posting>,
+/// Whether an account's balance is different in the snapshot, treating missing accounts as having no balance
+fn has_changed(node: &Node<Diff<Account>>) -> bool {
+    let balance = |account: &Option<Account>| account.clone().unwrap_or_default();
+    balance(&node.value.before) != balance(&node.value.after)
 }
 
-impl<'a> Balance<'a> {
-    pub fn new() -> Self {
-        Self {
-            tree: Tree::new(),
-            postings: Vec::new(),
+/// How the balance of one commodity changed for an account in a comparison
+struct Change {
+    commodity: String,
+    /// None if the account is only on one side
+    before: Option<Amount>,
+    after: Option<Amount>,
+    change: Amount,
+}
+
+/// The changes to each commodity an account has on either side of a comparison
+fn changes(diff: &Diff<Account>) -> Vec<Change> {
+    let mut commodities: BTreeSet<&str> = [&diff.before, &diff.after]
+        .iter()
+        .filter_map(|account| account.as_ref())
+        .flat_map(|account| account.balance())
+        .map(|amount| amount.commodity.as_str())
+        .collect();
+    // Balances that were zero on both sides still get a row
+    if commodities.is_empty() {
+        commodities.insert("");
+    }
+
+    commodities
+        .into_iter()
+        .map(|commodity| {
+            let amount =
+                |account: &Option<Account>| account.as_ref().map(|a| Amount::new(a.quantity(commodity), commodity));
+            let (before, after) = (amount(&diff.before), amount(&diff.after));
+
+            let quantity = |amount: &Option<Amount>| amount.as_ref().map_or(0, |a| a.quantity);
+            let change = Amount::new(quantity(&after) - quantity(&before), commodity);
+
+            Change {
+                commodity: commodity.to_owned(),
+                before,
+                after,
+                change,
+            }
+        })
+        .collect()
+}
+
+/// The before, after and change amounts of an account in a comparison, as text with a line for each commodity
+fn comparison_cells(diff: &Diff<Account>) -> [String; 3] {
+    let changes = changes(diff);
+    let text = |amount: &Option<Amount>| amount.as_ref().map(|a| a.to_string()).unwrap_or_default();
+    let column = |cell: &dyn Fn(&Change) -> String| changes.iter().map(cell).collect::<Vec<_>>().join("\n");
+    [
+        column(&|c| text(&c.before)),
+        column(&|c| text(&c.after)),
+        column(&|c| c.change.to_string()),
+    ]
+}
+
+/// Writes the balances in the snapshot and the journal and how they changed
+fn print_comparison(diff: &Tree<Diff<Account>>, format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {
+            let renderer = Renderer::new(Guides::Indent)
+                .column("before", |node: &Node<Diff<Account>>| {
+                    comparison_cells(&node.value)[0].clone()
+                })
+                .column("after", |node: &Node<Diff<Account>>| {
+                    comparison_cells(&node.value)[1].clone()
+                })
+                .column("change", |node: &Node<Diff<Account>>| {
+                    comparison_cells(&node.value)[2].clone()
+                });
+            let stdout = io::stdout();
+            renderer
+                .render(diff, diff.root(), &mut stdout.lock())
+                .expect("failed to write to stdout");
+        }
+        OutputFormat::Json => print_json(diff),
+        OutputFormat::Csv => print_csv(
+            &["account", "commodity", "before", "after", "change"],
+            diff.pre_order().flat_map(|(_, _, path, node)| {
+                let account = path.join(":");
+                changes(&node.value).into_iter().map(move |c| {
+                    let quantity = |amount: Option<Amount>| amount.map(|a| a.quantity_string()).unwrap_or_default();
+                    vec![
+                        account.clone(),
+                        c.commodity,
+                        quantity(c.before),
+                        quantity(c.after),
+                        c.change.quantity_string(),
+                    ]
+                })
+            }),
+        ),
+        OutputFormat::Html => {
+            let rows: Vec<Vec<String>> = diff
+                .pre_order()
+                .map(|(_, _, path, node)| {
+                    let changes = changes(&node.value);
+                    let amounts = |amount: &dyn Fn(&Change) -> Option<&Amount>| {
+                        html::amount_cell(&changes.iter().filter_map(amount).collect::<Vec<_>>())
+                    };
+                    vec![
+                        html::text_cell(&path.join(":")),
+                        amounts(&|c| c.before.as_ref()),
+                        amounts(&|c| c.after.as_ref()),
+                        amounts(&|c| Some(&c.change)),
+                    ]
+                })
+                .collect();
+            let body = html::table(&["Account", "Before", "After", "Change"], &rows);
+            print!("{}", html::document("Balance comparison", &body));
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::journal;
 
-    // FIXME for now, we're returning a boxed error because we could have tree/reader errors
-    pub fn read(&'a mut self, file: String) -> Result<(), Box<dyn std::error::Error>> {
-        let mut reader = Reader::new();
-        let config = Config::new();
+    const JOURNAL: &str = "2020-01-01 Salary
+    Assets:Current    £1000
+    Income:Work
 
-        let (_, postings, _) = reader.read(file, config)?;
-        self.postings = postings;
+2020-01-02 Shop
+    Expenses:Food    £30
+    Assets:Current
 
-        for posting in &self.postings {
-            let mut path: Vec<&str> = posting.path.split(':').collect();
-            let index = self.tree.add_path(&mut path);
+2020-01-03 Restaurant
+    Expenses:Dining    £10
+    Assets:Current
+";
 
-            self.tree.walk_ancestors(index, |node| {
-                node.value.amount.quantity += posting.amount.as_ref().unwrap().quantity
-            })?;
+    fn options() -> Options {
+        Options {
+            depth: None,
+            flat: false,
+            empty: false,
+            sort: SortKey::Name,
+            total: true,
+            percent: false,
         }
+    }
 
-        self.tree.display(&None, |node| {
-            let amount = &node.value.amount;
-            let quantity = amount.quantity as f64 / 100.;
-            let s = format!("{}{:.2}", amount.commodity, quantity);
-            Some(s)
-        });
+    /// The balance report on `contents` as text
+    fn text(contents: &str, options: Options) -> String {
+        let journal = journal(contents);
+        let mut output = Vec::new();
+        match Balance::new(options, None).run(&journal) {
+            BalanceReport::Balances(balances) => balances.write_text(&mut output).unwrap(),
+            BalanceReport::Comparison(_) => panic!("expected balances"),
+        }
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn it_shows_a_zero_total_as_0() {
+        assert_eq!(
+            text(JOURNAL, options()),
+            "  £960.00  Assets:Current
+   £40.00  Expenses
+   £10.00    Dining
+   £30.00    Food
+£-1000.00  Income:Work
+---------
+        0
+"
+        );
+    }
+
+    #[test]
+    fn it_shows_the_total_with_percentages() {
+        let options = Options {
+            percent: true,
+            ..options()
+        };
+        assert_eq!(
+            text(JOURNAL, options),
+            "100.00%  Assets:Current
+100.00%  Expenses
+ 25.00%    Dining
+ 75.00%    Food
+100.00%  Income:Work
+-------
+      0
+"
+        );
+    }
+
+    #[test]
+    fn it_collapses_accounts_deeper_than_the_depth() {
+        let options = Options {
+            depth: Some(1),
+            ..options()
+        };
+        assert_eq!(
+            text(JOURNAL, options),
+            "  £960.00  Assets
+   £40.00  Expenses
+£-1000.00  Income
+---------
+        0
+"
+        );
+    }
+
+    #[test]
+    fn it_shows_full_names_when_flat() {
+        let options = Options {
+            flat: true,
+            ..options()
+        };
+        assert_eq!(
+            text(JOURNAL, options),
+            "  £960.00  Assets:Current
+   £10.00  Expenses:Dining
+   £30.00  Expenses:Food
+£-1000.00  Income:Work
+---------
+        0
+"
+        );
+    }
+
+    #[test]
+    fn it_shows_empty_accounts_only_when_asked() {
+        let journal = format!(
+            "{}
+2020-01-04 Transfer
+    Assets:Savings    £50
+    Assets:Current
+
+2020-01-05 Transfer
+    Assets:Current    £50
+    Assets:Savings
+",
+            JOURNAL
+        );
+        assert_eq!(
+            text(&journal, options()),
+            "  £960.00  Assets:Current
+   £40.00  Expenses
+   £10.00    Dining
+   £30.00    Food
+£-1000.00  Income:Work
+---------
+        0
+"
+        );
+
+        let options = Options {
+            empty: true,
+            ..options()
+        };
+        assert_eq!(
+            text(&journal, options),
+            "  £960.00  Assets
+  £960.00    Current
+        0    Savings
+   £40.00  Expenses
+   £10.00    Dining
+   £30.00    Food
+£-1000.00  Income:Work
+---------
+        0
+"
+        );
+    }
+
+    #[test]
+    fn it_sorts_by_amount() {
+        let options = Options {
+            sort: SortKey::Amount,
+            ..options()
+        };
+        assert_eq!(
+            text(JOURNAL, options),
+            "£-1000.00  Income:Work
+   £40.00  Expenses
+   £10.00    Dining
+   £30.00    Food
+  £960.00  Assets:Current
+---------
+        0
+"
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn it_only_elides_parents_without_postings_of_their_own() {
+        let journal = format!(
+            "{}
+2020-01-04 Refund
+    Income    £5
+    Assets:Current
+",
+            JOURNAL
+        );
+        assert_eq!(
+            text(&journal, options()),
+            "  £955.00  Assets:Current
+   £40.00  Expenses
+   £10.00    Dining
+   £30.00    Food
+ £-995.00  Income
+£-1000.00    Work
+---------
+        0
+"
+        );
     }
 }
@@ -1,39 +1,296 @@
-use journal::Amount;
-use journal::Date;
-use reader::error::Error;
-use reader::reader::{Config, Reader};
-use std::collections::HashMap;
+//! Monthly budgets.
+//!
+//! A budget is written as periodic transactions, each posting of which sets aside an amount for an expense
+//! category in every month of the transaction's period:
+//!
+//! ```text
+//! ~ monthly from 2020-10
+//!     Groceries    £200
+//! ```
+//!
+//! A category is named by an expense account, which can be written without its top-level account (`Groceries`
+//! for `Expenses:Groceries`). Spending in a category includes its sub-accounts, and spending in an account with
+//! no budget is all overspending.
 
+use std::collections::{BTreeMap, BTreeSet};
+
+use journal::{Amount, Date, Journal, Period};
+use serde::Serialize;
+
+use crate::command::{Command, Report};
+use crate::html;
+use crate::output::{print_csv, print_json, OutputFormat};
+use crate::statement::AccountType;
+
+/// A month of the budget in one commodity
+#[derive(Serialize)]
 struct BudgetMonth {
+    /// The first day of the month
+    #[serde(serialize_with = "serialize_date")]
+    month: Date,
+    /// How much more was spent than budgeted, summed over the categories which went over
     overspending: Amount,
+    /// How much is budgeted for the months after this one, up to the end of the report
     future: Amount,
 }
 
-pub struct Budget {
-    months: HashMap<Date, BudgetMonth>,
+pub struct Budget;
+
+pub struct BudgetReport {
+    /// In order of month and then commodity
+    months: Vec<BudgetMonth>,
+}
+
+/// An amount set aside for a category in each month of a period
+struct Allocation<'j> {
+    period: &'j Period,
+    category: &'j str,
+    amount: &'j Amount,
 }
 
-impl Budget {
-    pub fn new() -> Self {
-        Self { months: HashMap::new() }
+impl Command for Budget {
+    type Report<'j> = BudgetReport;
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        let allocations: Vec<Allocation> = journal
+            .periodic_transactions()
+            .iter()
+            .flat_map(|periodic| {
+                periodic.postings.iter().filter_map(move |posting| {
+                    Some(Allocation {
+                        period: &periodic.period,
+                        category: category(&posting.path),
+                        amount: posting.amount.as_ref()?,
+                    })
+                })
+            })
+            .collect();
+
+        // The month, account and amount of every posting to an expense account
+        let spending: Vec<(Date, &str, &Amount)> = journal
+            .postings()
+            .iter()
+            .filter(|posting| is_expense(&posting.path))
+            .filter_map(|posting| {
+                let date = journal.transaction_of(posting)?.date;
+                Some((month_of(date), posting.path.as_str(), posting.amount.as_ref()?))
+            })
+            .collect();
+
+        let months = match report_months(&allocations, &spending) {
+            Some(months) => months,
+            None => return BudgetReport { months: Vec::new() },
+        };
+
+        let commodities: BTreeSet<&str> = allocations
+            .iter()
+            .map(|allocation| allocation.amount)
+            .chain(spending.iter().map(|(_, _, amount)| *amount))
+            .map(|amount| amount.commodity.as_str())
+            .collect();
+        let categories: BTreeSet<&str> = allocations.iter().map(|allocation| allocation.category).collect();
+
+        // The amounts budgeted for each month, by category and commodity
+        let budgeted: Vec<BTreeMap<(&str, &str), i64>> = months
+            .iter()
+            .map(|month| {
+                let mut budgeted = BTreeMap::new();
+                for allocation in allocations.iter().filter(|a| a.period.includes(*month)) {
+                    let key = (allocation.category, allocation.amount.commodity.as_str());
+                    *budgeted.entry(key).or_insert(0) += allocation.amount.quantity;
+                }
+                budgeted
+            })
+            .collect();
+
+        let mut report = Vec::with_capacity(months.len() * commodities.len());
+        for (i, month) in months.iter().enumerate() {
+            let mut spent: BTreeMap<(&str, &str), i64> = BTreeMap::new();
+            for (_, account, amount) in spending.iter().filter(|(date, _, _)| date == month) {
+                let key = (category_of(account, &categories), amount.commodity.as_str());
+                *spent.entry(key).or_insert(0) += amount.quantity;
+            }
+
+            for commodity in &commodities {
+                let overspending = spent
+                    .iter()
+                    .filter(|((_, c), _)| c == commodity)
+                    .map(|(key, spent)| (spent - budgeted[i].get(key).unwrap_or(&0)).max(0))
+                    .sum();
+                let future = budgeted[i + 1..]
+                    .iter()
+                    .flat_map(|budgeted| budgeted.iter())
+                    .filter(|((_, c), _)| c == commodity)
+                    .map(|(_, quantity)| quantity)
+                    .sum();
+
+                report.push(BudgetMonth {
+                    month: *month,
+                    overspending: Amount::new(overspending, commodity),
+                    future: Amount::new(future, commodity),
+                });
+            }
+        }
+
+        BudgetReport { months: report }
     }
+}
 
-    pub fn read(&mut self, file: String) -> Result<(), Error> {
-        let mut reader = Reader::new();
-        let config = Config::new();
+impl Report for BudgetReport {
+    fn print(&self, format: OutputFormat) {
+        let months = &self.months;
 
-        let (transactions, postings, _) = reader.read(file, config)?;
-        // self.postings = postings;
+        match format {
+            OutputFormat::Text => {
+                if !months.is_empty() {
+                    println!("{:<7}  {:>12}  {:>12}", "Month", "Overspending", "Future");
+                }
+                for month in months {
+                    println!(
+                        "{:<7}  {:>12}  {:>12}",
+                        month.month.format("%Y-%m"),
+                        month.overspending.to_string(),
+                        month.future.to_string()
+                    );
+                }
+            }
+            OutputFormat::Json => print_json(months),
+            OutputFormat::Csv => print_csv(
+                &["month", "commodity", "overspending", "future"],
+                months.iter().map(|month| {
+                    vec![
+                        month.month.to_string(),
+                        month.overspending.commodity.clone(),
+                        month.overspending.quantity_string(),
+                        month.future.quantity_string(),
+                    ]
+                }),
+            ),
+            OutputFormat::Html => {
+                let rows: Vec<Vec<String>> = months
+                    .iter()
+                    .map(|month| {
+                        vec![
+                            html::text_cell(&month.month.format("%Y-%m")),
+                            html::amount_cell(&[&month.overspending]),
+                            html::amount_cell(&[&month.future]),
+                        ]
+                    })
+                    .collect();
+                let body = html::table(&["Month", "Overspending", "Future"], &rows);
+                print!("{}", html::document("Budget", &body));
+            }
+        }
+    }
+}
 
-        for tx in transactions {}
+/// Every month from the first with a budget or spending to the last, or None if there is no budget
+/// Budgets without an end don't extend the report
+fn report_months(allocations: &[Allocation], spending: &[(Date, &str, &Amount)]) -> Option<Vec<Date>> {
+    if allocations.is_empty() {
+        return None;
+    }
 
-        // for posting in &self.postings {
-        //     let mut path: Vec<&str> = posting.path.split(':').collect();
-        // self.tree.add_path(&mut path);
-        // }
+    let bounds = allocations.iter().flat_map(|allocation| {
+        let period = allocation.period;
+        let last = period.end_date.map(|end| month_of(end.previous_day()));
+        period.start_date.into_iter().chain(last)
+    });
+    let dates: Vec<Date> = bounds.chain(spending.iter().map(|(month, _, _)| *month)).collect();
+    let (first, last) = (*dates.iter().min()?, *dates.iter().max()?);
 
-        // self.tree.display(&None, |_| None);
+    let mut months = vec![first];
+    while let Some(month) = months.last().filter(|month| **month < last) {
+        let next = next_month(*month);
+        months.push(next);
+    }
+    Some(months)
+}
+
+/// Whether the account is under a top-level expense account
+fn is_expense(account: &str) -> bool {
+    let top_level = account.split(':').next().unwrap_or("");
+    AccountType::of(top_level) == Some(AccountType::Expense)
+}
+
+/// The category an expense account names, which leaves out the top-level account
+fn category(account: &str) -> &str {
+    match account.split_once(':') {
+        Some((_, category)) if is_expense(account) => category,
+        _ => account,
+    }
+}
+
+/// The budgeted category an expense account's spending counts against: the closest of the account and its
+/// parents which has a budget, or the account itself if none do
+fn category_of<'a>(account: &'a str, categories: &BTreeSet<&'a str>) -> &'a str {
+    let account = category(account);
+    let mut candidate = account;
+    loop {
+        if categories.contains(candidate) {
+            return candidate;
+        }
+        match candidate.rsplit_once(':') {
+            Some((parent, _)) => candidate = parent,
+            None => return account,
+        }
+    }
+}
+
+/// The first day of the month `date` is in
+fn month_of(date: Date) -> Date {
+    Date::try_from_ymd(date.year(), date.month(), 1).unwrap()
+}
+
+/// The first day of the month after the one starting on `month`
+fn next_month(month: Date) -> Date {
+    match month.month() {
+        12 => Date::try_from_ymd(month.year() + 1, 1, 1),
+        m => Date::try_from_ymd(month.year(), m + 1, 1),
+    }
+    .unwrap()
+}
+
+fn serialize_date<S: serde::Serializer>(date: &Date, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reader::reader::{Config, Reader};
+    use std::path::Path;
+
+    fn budget(name: &str) -> Vec<(String, String, String)> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(name);
+        let journal = Reader::new().read_journal(vec![path], Config::new()).unwrap();
+        Budget
+            .run(&journal)
+            .months
+            .iter()
+            .map(|m| {
+                (
+                    m.month.format("%Y-%m"),
+                    m.overspending.to_string(),
+                    m.future.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_compares_spending_with_the_budget() {
+        let month = |month: &str, overspending: &str, future: &str| {
+            (month.to_owned(), overspending.to_owned(), future.to_owned())
+        };
+        assert_eq!(
+            budget("budget-test.journal"),
+            vec![month("2020-10", "£20.00", "£45.00"), month("2020-11", "£0.00", "£0.00")]
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn it_has_no_months_without_a_budget() {
+        assert!(budget("test.journal").is_empty());
     }
 }
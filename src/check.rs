@@ -0,0 +1,41 @@
+use reader::error::Error;
+use reader::reader::{Config, Reader};
+
+pub struct Check {
+    json: bool,
+}
+
+impl Check {
+    /// If `json` is set, errors are printed as a JSON array rather than rendered for people
+    pub fn new(json: bool) -> Self {
+        Self { json }
+    }
+
+    /// Reports every error in the journal and returns whether there were none
+    pub fn read(&mut self, files: Vec<String>) -> Result<bool, Error> {
+        let mut reader = Reader::new();
+        let mut config = Config::new();
+        config.recover = true;
+        config.read_postings = false;
+
+        let (transactions, _, _) = reader.read_files(files, config)?;
+        let errors = reader.errors();
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(errors).unwrap());
+            return Ok(errors.is_empty());
+        }
+
+        for error in errors {
+            println!("{}", error.render());
+        }
+
+        match errors.len() {
+            0 => println!("No errors found in {} transactions", transactions.len()),
+            1 => println!("Found 1 error"),
+            n => println!("Found {} errors", n),
+        }
+
+        Ok(errors.is_empty())
+    }
+}
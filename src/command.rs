@@ -1,12 +1,95 @@
-use crate::journal::Transaction;
-use crate::reader::error::Error;
+//! The subcommands which report on a journal.
+//!
+//! A command is given a journal that has already been read and returns a report, which is printed separately in
+//! whichever format the user asked for. Commands don't touch files themselves, so they can be run on journals
+//! read from anywhere and their reports can be used without printing them.
+//!
+//! Commands whose reports don't need the whole journal at once are `StreamCommand`s instead. They are given each
+//! transaction as it's read and keep only what their report needs. This is a separate trait, not another input to
+//! `Command`, because the two kinds of command differ in ways that show up in their signatures. A stream command's
+//! report can't borrow from a journal, so it owns its data. Reading can also fail after the command has started,
+//! so its `run` returns a `Result`, while a `Command` is only run once the journal has been read without errors.
+//!
+//! `check` and `fmt` aren't commands: they work on the files rather than on what was read from them.
 
-use std::sync::Arc;
+use journal::Journal;
+use reader::error::Error;
+use reader::reader::{Config, Reader};
+use reader::stream::Stream;
+
+use crate::output::OutputFormat;
 
 pub trait Command {
-    fn read_transactions<I>(&mut self, reader: I) -> Result<(), Error>
-    where
-        I: IntoIterator<Item = Result<Arc<Transaction>, Error>>;
+    /// The report may borrow from the journal it describes
+    type Report<'j>: Report;
+
+    /// How the journal should be read for this command
+    fn config(&self) -> Config {
+        Config::new()
+    }
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j>;
+}
+
+pub trait StreamCommand {
+    type Report: Report;
+
+    /// Reads the stream to its end, returning the first error in it
+    fn run(&self, stream: Stream) -> Result<Self::Report, Error>;
+}
+
+pub trait Report {
+    /// Prints the report to stdout
+    /// `format` is one of those accepted by the report's subcommand
+    fn print(&self, format: OutputFormat);
+}
+
+/// Reads the journals at `files` and prints the command's report on them
+pub fn execute<C: Command>(command: &C, files: &[String], format: OutputFormat) -> Result<(), Error> {
+    let journal = Reader::new().read_journal(files, command.config())?;
+    command.run(&journal).print(format);
+    Ok(())
+}
+
+/// Streams the journals at `files` through the command and prints its report on them
+pub fn execute_stream<C: StreamCommand>(command: &C, files: &[String], format: OutputFormat) -> Result<(), Error> {
+    let report = command.run(Stream::open(files, false))?;
+    report.print(format);
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::query::Query;
+    use clap::App;
+
+    /// Builds a query from the arguments given after a subcommand
+    pub fn query(args: &[&str]) -> Query {
+        let app = App::new("test").args(&Query::args());
+        let matches = app.get_matches_from(Some("test").iter().chain(args));
+        Query::from_matches(&matches).unwrap()
+    }
+
+    /// Reads a journal from a string, as commands are given one
+    pub fn journal(contents: &str) -> Journal {
+        let mut reader = Reader::new();
+        let (transactions, postings, sources) = reader.read_str(contents, Config::new()).unwrap();
+        let sources = sources
+            .into_iter()
+            .map(|source| {
+                let count = reader.transaction_counts().get(&source).copied().unwrap_or(0);
+                (source, count)
+            })
+            .collect();
 
-    fn report(&self);
+        Journal::new(
+            transactions,
+            postings,
+            sources,
+            reader.payees().to_vec(),
+            reader.prices().to_vec(),
+        )
+        .with_periodic_transactions(reader.periodic_transactions().to_vec())
+    }
 }
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use reader::error::{Error, ErrorKind};
+use reader::reader::STDIN;
+use reader::syntax::{File, FormatOptions};
+
+pub struct Formatter {
+    /// Report unformatted files rather than rewriting them
+    check: bool,
+    options: FormatOptions,
+}
+
+impl Formatter {
+    pub fn new(check: bool, options: FormatOptions) -> Self {
+        Self { check, options }
+    }
+
+    /// Formats the files and every file they include, in place
+    /// Returns whether they were all formatted already
+    pub fn read(&mut self, files: Vec<String>) -> Result<bool, Error> {
+        let mut already_formatted = true;
+        let mut visited = HashSet::new();
+
+        // Files are formatted in the order they're read
+        let mut queue: Vec<PathBuf> = files.into_iter().rev().map(PathBuf::from).collect();
+
+        while let Some(location) = queue.pop() {
+            if location == Path::new(STDIN) {
+                already_formatted &= self.format_stdin()?;
+                continue;
+            }
+
+            if !visited.insert(location.canonicalize().unwrap_or_else(|_| location.clone())) {
+                continue;
+            }
+
+            let file = File::read(&location)?;
+            let formatted = file.format(&self.options);
+
+            if formatted != file.to_string() {
+                already_formatted = false;
+
+                if self.check {
+                    println!("{} is not formatted", location.display());
+                } else {
+                    std::fs::write(&location, formatted).map_err(|e| Error::new(ErrorKind::IO(e), &location, 0))?;
+                    println!("Formatted {}", location.display());
+                }
+            }
+
+            let includes = file.includes(&location)?;
+            queue.extend(includes.into_iter().rev());
+        }
+
+        Ok(already_formatted)
+    }
+
+    /// Writes stdin to stdout formatted. Includes are left alone as there's nowhere to write them.
+    fn format_stdin(&self) -> Result<bool, Error> {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .map_err(|e| Error::new(ErrorKind::IO(e), "<stdin>", 0))?;
+
+        let file = File::parse(&contents);
+        let formatted = file.format(&self.options);
+        let already_formatted = formatted == contents;
+
+        if !self.check {
+            print!("{}", formatted);
+        }
+
+        Ok(already_formatted)
+    }
+}
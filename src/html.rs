@@ -0,0 +1,194 @@
+//! Self-contained HTML reports, selected with `-O html`.
+//!
+//! Pages have their styles inline and no scripts: account hierarchies collapse using `<details>` elements.
+
+use std::fmt::Write;
+
+use journal::Amount;
+
+const STYLE: &str = "
+body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Helvetica, Arial, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.4em; }
+.tree { max-width: 40em; }
+.row { display: flex; justify-content: space-between; padding: 0.15em 0; border-bottom: 1px solid #eee; }
+details > summary { cursor: pointer; list-style: none; }
+details > summary::-webkit-details-marker { display: none; }
+details > summary .account::before { content: '\\25B8'; display: inline-block; width: 1em; }
+details[open] > summary .account::before { content: '\\25BE'; }
+.leaf .account::before { content: ''; display: inline-block; width: 1em; }
+table { border-collapse: collapse; }
+th, td { padding: 0.2em 0.8em; border-bottom: 1px solid #eee; text-align: left; }
+.amount { text-align: right; font-variant-numeric: tabular-nums; white-space: nowrap; }
+.negative { color: #c0392b; }
+.total { font-weight: bold; border-top: 2px solid #222; }
+";
+
+/// Wraps `body` in a complete HTML document
+pub fn document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+        style = STYLE,
+        body = body
+    )
+}
+
+/// A node of a tree being rendered
+pub struct TreeRow<'r> {
+    pub name: &'r str,
+    /// Zero for top-level nodes
+    pub depth: usize,
+    pub amounts: Vec<&'r Amount>,
+    /// Whether the following rows up to the next one at this depth or shallower are its children
+    pub has_children: bool,
+}
+
+/// Renders tree rows, in pre-order, as nested collapsible rows
+pub fn tree(rows: &[TreeRow]) -> String {
+    let mut output = String::from("<div class=\"tree\">\n");
+    // The depths of the <details> elements which haven't been closed yet
+    let mut open: Vec<usize> = Vec::new();
+
+    for row in rows {
+        while open.last().is_some_and(|d| *d >= row.depth) {
+            open.pop();
+            output.push_str("</details>\n");
+        }
+
+        let cells = format!(
+            "<span class=\"account\" style=\"padding-left: {}em\">{}</span>{}",
+            row.depth,
+            escape(row.name),
+            amount_spans(&row.amounts)
+        );
+
+        if row.has_children {
+            writeln!(output, "<details open>\n<summary class=\"row\">{}</summary>", cells).unwrap();
+            open.push(row.depth);
+        } else {
+            writeln!(output, "<div class=\"row leaf\">{}</div>", cells).unwrap();
+        }
+    }
+
+    for _ in open {
+        output.push_str("</details>\n");
+    }
+    output.push_str("</div>\n");
+    output
+}
+
+/// Renders a total row to follow a tree
+pub fn total(amounts: &[&Amount]) -> String {
+    format!(
+        "<div class=\"tree\">\n<div class=\"row total\"><span class=\"account\">Total</span><span>{}</span></div>\n</div>\n",
+        amount_spans(amounts)
+    )
+}
+
+/// Renders a table with a header row; cells are HTML and should already be escaped
+pub fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut output = String::from("<table>\n<thead>\n<tr>");
+    for column in header {
+        write!(output, "<th>{}</th>", escape(column)).unwrap();
+    }
+    output.push_str("</tr>\n</thead>\n<tbody>\n");
+
+    for row in rows {
+        output.push_str("<tr>");
+        for cell in row {
+            output.push_str(cell);
+        }
+        output.push_str("</tr>\n");
+    }
+
+    output.push_str("</tbody>\n</table>\n");
+    output
+}
+
+/// A table cell containing text
+pub fn text_cell(text: &str) -> String {
+    format!("<td>{}</td>", escape(text))
+}
+
+/// A right-aligned table cell containing amounts, highlighted if any are negative
+pub fn amount_cell(amounts: &[&Amount]) -> String {
+    let negative = amounts.iter().any(|a| a.quantity < 0);
+    let text: Vec<String> = amounts.iter().map(|a| a.to_string()).collect();
+    format!(
+        "<td class=\"amount{}\">{}</td>",
+        if negative { " negative" } else { "" },
+        escape(&text.join(", "))
+    )
+}
+
+/// Amounts in separate spans, separated by spaces
+fn amount_spans(amounts: &[&Amount]) -> String {
+    let spans: Vec<String> = amounts.iter().map(|a| amount_span(a)).collect();
+    spans.join(" ")
+}
+
+fn amount_span(amount: &Amount) -> String {
+    format!(
+        "<span class=\"amount{}\">{}</span>",
+        if amount.quantity < 0 { " negative" } else { "" },
+        escape(&amount.to_string())
+    )
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_nests_children_in_details() {
+        let (food, rent) = (Amount::new(3000, "£"), Amount::new(-5000, "£"));
+        let row = |name, depth, amounts, has_children| TreeRow {
+            name,
+            depth,
+            amounts,
+            has_children,
+        };
+        let rows = [
+            row("Expenses", 0, vec![&food], true),
+            row("Food", 1, vec![&food], false),
+            row("Rent & <Bills>", 0, vec![&rent], false),
+        ];
+
+        assert_eq!(
+            tree(&rows),
+            "<div class=\"tree\">
+<details open>
+<summary class=\"row\"><span class=\"account\" style=\"padding-left: 0em\">Expenses</span><span class=\"amount\">£30.00</span></summary>
+<div class=\"row leaf\"><span class=\"account\" style=\"padding-left: 1em\">Food</span><span class=\"amount\">£30.00</span></div>
+</details>
+<div class=\"row leaf\"><span class=\"account\" style=\"padding-left: 0em\">Rent &amp; &lt;Bills&gt;</span><span class=\"amount negative\">£-50.00</span></div>
+</div>
+"
+        );
+    }
+
+    #[test]
+    fn it_highlights_cells_with_negative_amounts() {
+        let (positive, negative) = (Amount::new(500, "$"), Amount::new(-100, "£"));
+        assert_eq!(amount_cell(&[&positive]), "<td class=\"amount\">$5.00</td>");
+        assert_eq!(
+            amount_cell(&[&positive, &negative]),
+            "<td class=\"amount negative\">$5.00, £-1.00</td>"
+        );
+    }
+}
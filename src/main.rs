@@ -1,22 +1,42 @@
 extern crate clap;
 extern crate journal;
 extern crate reader;
+extern crate serde_json;
 extern crate tree;
 
 mod accounts;
 mod balance;
 mod budget;
+mod check;
 mod command;
+mod fmt;
+mod html;
+mod output;
+mod payees;
 mod print;
+mod query;
+mod register;
+mod statement;
 mod stats;
+mod tags;
 
 use crate::accounts::Accounts;
-use crate::balance::Balance;
+use crate::balance::{Balance, Snapshot};
 use crate::budget::Budget;
-use crate::print::Printer;
+use crate::check::Check;
+use crate::fmt::Formatter;
+use crate::output::OutputFormat;
+use crate::payees::Payees;
+use crate::print::{Printer, SortKey};
+use crate::query::Query;
+use crate::register::Register;
+use crate::statement::{Interval, Statement, StatementKind};
 use crate::stats::Statistics;
+use crate::tags::Tags;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use reader::syntax::FormatOptions;
+use std::path::Path;
 
 fn main() {
     let matches = App::new("rledger")
@@ -27,67 +47,373 @@ fn main() {
             Arg::with_name("file")
                 .short("f")
                 .long("file")
-                .help("The journal file to read.")
-                .env("LEDGER_FILE")
+                .help(
+                    "The journal file to read, or - to read from stdin. Can be repeated to merge several files. \
+                     Defaults to the list of paths in the LEDGER_FILE environment variable.",
+                )
+                .multiple(true)
+                .number_of_values(1)
                 .value_name("LEDGER_FILE"),
         )
-        .subcommand(App::new("print").about("Show transaction entries").alias("p"))
+        .subcommand(
+            App::new("print")
+                .about("Show transaction entries")
+                .alias("p")
+                .args(&Query::args())
+                .arg(
+                    Arg::with_name("explicit")
+                        .short("x")
+                        .long("explicit")
+                        .help("Show amounts which were elided in the journal"),
+                )
+                .arg(
+                    Arg::with_name("sort")
+                        .short("S")
+                        .long("sort")
+                        .help("What to order transactions by")
+                        .possible_values(&["date", "payee", "amount"])
+                        .default_value("date"),
+                )
+                .arg(OutputFormat::arg()),
+        )
+        .subcommand(
+            App::new("register")
+                .about("Show postings and a running total")
+                .aliases(&["reg", "r"])
+                .args(&Query::args())
+                .arg(OutputFormat::html_arg()),
+        )
         .subcommand(
             App::new("statistics")
                 .about("Show statistics about the journal")
-                .aliases(&["stats"]),
+                .aliases(&["stats"])
+                .arg(OutputFormat::arg()),
+        )
+        .subcommand(
+            App::new("accounts")
+                .about("List all accounts")
+                .aliases(&["acc", "a"])
+                .arg(OutputFormat::arg()),
+        )
+        .subcommand(
+            App::new("payees")
+                .about("List payees with their transaction counts and totals")
+                .args(&Query::args())
+                .arg(OutputFormat::arg()),
+        )
+        .subcommand(
+            App::new("tags")
+                .about("List tags with their usage counts and values")
+                .args(&Query::args())
+                .arg(OutputFormat::arg()),
         )
-        .subcommand(App::new("accounts").about("List all accounts").aliases(&["acc", "a"]))
         .subcommand(
             App::new("balance")
                 .about("Show accounts and their balances")
-                .aliases(&["bal"]),
+                .aliases(&["bal"])
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .help("Collapse accounts deeper than this into their ancestors")
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("flat")
+                        .long("flat")
+                        .help("Show full account names instead of a hierarchy"),
+                )
+                .arg(
+                    Arg::with_name("empty")
+                        .short("E")
+                        .long("empty")
+                        .help("Show accounts with a zero balance"),
+                )
+                .arg(
+                    Arg::with_name("sort")
+                        .short("S")
+                        .long("sort")
+                        .help("What to order accounts by")
+                        .possible_values(&["name", "amount"])
+                        .default_value("name"),
+                )
+                .arg(
+                    Arg::with_name("no-total")
+                        .long("no-total")
+                        .help("Don't show the total of every account"),
+                )
+                .arg(
+                    Arg::with_name("percent")
+                        .short("%")
+                        .long("percent")
+                        .help("Show balances as a percentage of the parent account's in text and CSV output"),
+                )
+                .arg(
+                    Arg::with_name("compare")
+                        .long("compare")
+                        .help("Compare balances with ones saved from balance -O json or -O csv")
+                        .value_name("FILE"),
+                )
+                .arg(OutputFormat::html_arg()),
+        )
+        .subcommand(
+            App::new("budget")
+                .about("Show budget status")
+                .aliases(&["bud"])
+                .arg(OutputFormat::html_arg()),
+        )
+        .subcommand(statement_command(
+            "incomestatement",
+            "is",
+            "Show revenues, expenses and net income",
+        ))
+        .subcommand(statement_command(
+            "balancesheet",
+            "bs",
+            "Show assets, liabilities, equity and net worth",
+        ))
+        .subcommand(statement_command(
+            "cashflow",
+            "cf",
+            "Show changes in cash accounts: asset accounts whose names contain cash, bank, checking, current or \
+             saving, ignoring case",
+        ))
+        .subcommand(
+            App::new("check").about("Report every error in the journal").arg(
+                Arg::with_name("output-format")
+                    .short("O")
+                    .long("output-format")
+                    .help("How to print errors")
+                    .possible_values(&["text", "json"])
+                    .default_value("text"),
+            ),
+        )
+        .subcommand(
+            App::new("fmt")
+                .about("Normalise the formatting of the journal files in place")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("List unformatted files instead of rewriting them and fail if there are any"),
+                )
+                .arg(
+                    Arg::with_name("column")
+                        .long("column")
+                        .help("The column to right-align posting amounts to")
+                        .value_name("COLUMN")
+                        .default_value("52"),
+                ),
         )
-        .subcommand(App::new("budget").about("Show budget status").aliases(&["bud"]))
         .get_matches();
 
-    if matches.value_of("file").is_none() && matches.occurrences_of("file") == 0 {
+    let files = journal_files(&matches);
+    if files.is_empty() {
         println!("No journal file was passed and none could be found in the environment");
         return;
     }
 
-    if let Some(_) = matches.subcommand_matches("print") {
-        let file = matches.value_of("file").unwrap().to_owned();
-        let mut printer = Printer::new();
-        if let Err(e) = printer.read(file) {
+    if let Some(print_matches) = matches.subcommand_matches("print") {
+        let query = query_or_exit(print_matches);
+        let explicit = print_matches.is_present("explicit");
+        let sort = SortKey::from_name(print_matches.value_of("sort").unwrap()).unwrap();
+
+        let printer = Printer::new(query, explicit, sort);
+        if let Err(e) = command::execute(&printer, &files, OutputFormat::from_matches(print_matches)) {
+            println!("{}", e);
+        }
+    }
+
+    if let Some(register_matches) = matches.subcommand_matches("register") {
+        let query = query_or_exit(register_matches);
+        let register = Register::new(query);
+        if let Err(e) = command::execute(&register, &files, OutputFormat::from_matches(register_matches)) {
+            println!("{}", e);
+        }
+    }
+
+    if let Some(accounts_matches) = matches.subcommand_matches("accounts") {
+        if let Err(e) = command::execute_stream(&Accounts, &files, OutputFormat::from_matches(accounts_matches)) {
+            println!("{}", e);
+        }
+    }
+
+    if let Some(payees_matches) = matches.subcommand_matches("payees") {
+        let query = query_or_exit(payees_matches);
+        let payees = Payees::new(query);
+        if let Err(e) = command::execute(&payees, &files, OutputFormat::from_matches(payees_matches)) {
+            println!("{}", e);
+        }
+    }
+
+    if let Some(tags_matches) = matches.subcommand_matches("tags") {
+        let query = query_or_exit(tags_matches);
+        let tags = Tags::new(query);
+        if let Err(e) = command::execute(&tags, &files, OutputFormat::from_matches(tags_matches)) {
             println!("{}", e);
         }
     }
 
-    if let Some(_) = matches.subcommand_matches("accounts") {
-        let file = matches.value_of("file").unwrap().to_owned();
-        let mut accounts = Accounts::new();
-        if let Err(e) = accounts.read(file) {
+    if let Some(balance_matches) = matches.subcommand_matches("balance") {
+        let options = balance::Options {
+            depth: match balance_matches.value_of("depth").map(str::parse) {
+                None => None,
+                Some(Ok(depth)) => Some(depth),
+                Some(Err(_)) => {
+                    println!("The depth must be a number");
+                    std::process::exit(1);
+                }
+            },
+            flat: balance_matches.is_present("flat"),
+            empty: balance_matches.is_present("empty"),
+            sort: match balance_matches.value_of("sort") {
+                Some("amount") => balance::SortKey::Amount,
+                _ => balance::SortKey::Name,
+            },
+            total: !balance_matches.is_present("no-total"),
+            percent: balance_matches.is_present("percent"),
+        };
+
+        let snapshot = match balance_matches
+            .value_of("compare")
+            .map(|f| Snapshot::load(Path::new(f)))
+        {
+            None => None,
+            Some(Ok(snapshot)) => Some(snapshot),
+            Some(Err(e)) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        };
+
+        let balance = Balance::new(options, snapshot);
+        if let Err(e) = command::execute(&balance, &files, OutputFormat::from_matches(balance_matches)) {
             println!("{}", e);
         }
     }
 
-    if let Some(_) = matches.subcommand_matches("balance") {
-        let file = matches.value_of("file").unwrap().to_owned();
-        let mut balance = Balance::new();
-        if let Err(e) = balance.read(file) {
+    if let Some(budget_matches) = matches.subcommand_matches("budget") {
+        if let Err(e) = command::execute(&Budget, &files, OutputFormat::from_matches(budget_matches)) {
             println!("{}", e);
         }
     }
 
-    if let Some(_) = matches.subcommand_matches("budget") {
-        let file = matches.value_of("file").unwrap().to_owned();
-        let mut budget = Budget::new();
-        if let Err(e) = budget.read(file) {
+    if let Some(stats_matches) = matches.subcommand_matches("statistics") {
+        let stats = Statistics::new(files.clone());
+        if let Err(e) = command::execute_stream(&stats, &files, OutputFormat::from_matches(stats_matches)) {
             println!("{}", e);
         }
     }
 
-    if let Some(_) = matches.subcommand_matches("statistics") {
-        let file = matches.value_of("file").unwrap().to_owned();
-        let mut stats = Statistics::new();
-        if let Err(e) = stats.read(file) {
+    for (name, kind) in &[
+        ("incomestatement", StatementKind::IncomeStatement),
+        ("balancesheet", StatementKind::BalanceSheet),
+        ("cashflow", StatementKind::CashFlow),
+    ] {
+        if let Some(statement_matches) = matches.subcommand_matches(name) {
+            let interval = if statement_matches.is_present("monthly") {
+                Some(Interval::Monthly)
+            } else if statement_matches.is_present("quarterly") {
+                Some(Interval::Quarterly)
+            } else if statement_matches.is_present("yearly") {
+                Some(Interval::Yearly)
+            } else {
+                None
+            };
+
+            let statement = Statement::new(*kind, interval);
+            if let Err(e) = command::execute(&statement, &files, OutputFormat::from_matches(statement_matches)) {
+                println!("{}", e);
+            }
+        }
+    }
+
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        let mut check = Check::new(check_matches.value_of("output-format") == Some("json"));
+        match check.read(files.clone()) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(fmt_matches) = matches.subcommand_matches("fmt") {
+        let check = fmt_matches.is_present("check");
+        let options = FormatOptions {
+            amount_column: match fmt_matches.value_of("column").unwrap().parse() {
+                Ok(column) => column,
+                Err(_) => {
+                    println!("The column must be a number");
+                    std::process::exit(1);
+                }
+            },
+        };
+
+        let mut formatter = Formatter::new(check, options);
+        match formatter.read(files.clone()) {
+            Ok(already_formatted) => {
+                if check && !already_formatted {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// A financial statement subcommand, which can show a column per period
+fn statement_command(name: &'static str, alias: &'static str, about: &'static str) -> App<'static, 'static> {
+    App::new(name)
+        .about(about)
+        .alias(alias)
+        .arg(
+            Arg::with_name("monthly")
+                .short("M")
+                .long("monthly")
+                .help("Show a column for each month"),
+        )
+        .arg(
+            Arg::with_name("quarterly")
+                .short("Q")
+                .long("quarterly")
+                .help("Show a column for each quarter"),
+        )
+        .arg(
+            Arg::with_name("yearly")
+                .short("Y")
+                .long("yearly")
+                .help("Show a column for each year"),
+        )
+        .group(ArgGroup::with_name("interval").args(&["monthly", "quarterly", "yearly"]))
+        .arg(OutputFormat::arg())
+}
+
+/// Builds the query from a subcommand's arguments, exiting if they are invalid
+fn query_or_exit(matches: &ArgMatches) -> Query {
+    match Query::from_matches(matches) {
+        Ok(query) => query,
+        Err(e) => {
             println!("{}", e);
+            std::process::exit(1);
         }
     }
 }
+
+/// The files passed with -f or, failing that, the paths in LEDGER_FILE
+fn journal_files(matches: &ArgMatches) -> Vec<String> {
+    if let Some(files) = matches.values_of("file") {
+        return files.map(String::from).collect();
+    }
+
+    match std::env::var_os("LEDGER_FILE") {
+        None => vec![],
+        Some(paths) => std::env::split_paths(&paths)
+            .filter(|path| !path.as_os_str().is_empty())
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+    }
+}
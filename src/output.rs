@@ -0,0 +1,86 @@
+//! Machine-readable output for reports, selected with `-O json` or `-O csv`.
+//!
+//! Amounts are written as `{"commodity": string, "quantity": string}` in JSON, or as separate commodity and
+//! quantity columns in CSV, with quantities as decimals such as "-40.00". Dates are written as YYYY-MM-DD and
+//! statuses as "cleared", "pending" or "none" ("*", "!" or "" in CSV). Balances have an amount for each commodity,
+//! and a CSV row for each. Budgets have an entry for each month and commodity, with the month as its first day.
+//!
+//! | Report     | JSON                                                               | CSV columns                                                 |
+//! |------------|--------------------------------------------------------------------|-------------------------------------------------------------|
+//! | print      | `[{date, status, payee, header_comment, comments, postings: [{account, amount, comments}]}]` | transaction, date, status, payee, account, commodity, quantity |
+//! | register   | `[{date, payee, account, amount, total: [amount]}]`                | date, payee, account, commodity, quantity, total            |
+//! | balance    | `[{name, path, value: [amount], children: [...]}]`                 | account, commodity, quantity                                |
+//! | balance --compare | `[{name, path, value: {before: [amount], after: [amount]}, children: [...]}]` | account, commodity, before, after, change |
+//! | accounts   | `[account]`                                                        | account                                                     |
+//! | statistics | `{files, sources: [{path, transactions}], first_transaction, last_transaction, days, ...}` | statistic, value |
+//! | budget     | `[{month, overspending: amount, future: amount}]`                  | month, commodity, overspending, future                      |
+
+use clap::{Arg, ArgMatches};
+use serde::Serialize;
+
+/// How a report is written to stdout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    /// A single pretty-printed JSON value
+    Json,
+    /// A header row followed by one row per record
+    Csv,
+    /// A self-contained HTML page, see the html module
+    Html,
+}
+
+impl OutputFormat {
+    /// The argument that selects the output format, for adding to a report's subcommand
+    pub fn arg() -> Arg<'static, 'static> {
+        Self::arg_with_formats(&["text", "json", "csv"])
+    }
+
+    /// Like `arg`, for reports which can also be rendered as HTML
+    pub fn html_arg() -> Arg<'static, 'static> {
+        Self::arg_with_formats(&["text", "json", "csv", "html"])
+    }
+
+    fn arg_with_formats(formats: &'static [&'static str]) -> Arg<'static, 'static> {
+        Arg::with_name("output-format")
+            .short("O")
+            .long("output-format")
+            .help("How to print the report")
+            .possible_values(formats)
+            .default_value("text")
+    }
+
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        match matches.value_of("output-format") {
+            Some("json") => OutputFormat::Json,
+            Some("csv") => OutputFormat::Csv,
+            Some("html") => OutputFormat::Html,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+/// Writes `value` to stdout as JSON
+/// Like `println!`, this panics if stdout can't be written to
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    serde_json::to_writer_pretty(&mut handle, value).expect("failed to write to stdout");
+    println!();
+}
+
+/// Writes `header` and then `rows` to stdout as CSV
+/// Like `println!`, this panics if stdout can't be written to
+pub fn print_csv<I, R>(header: &[&str], rows: I)
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(header).expect("failed to write to stdout");
+    for row in rows {
+        writer.write_record(row).expect("failed to write to stdout");
+    }
+    writer.flush().expect("failed to write to stdout");
+}
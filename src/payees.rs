@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use journal::{Amount, Journal, Posting, Transaction};
+use serde::Serialize;
+
+use crate::command::{Command, Report};
+use crate::output::{print_csv, print_json, OutputFormat};
+use crate::query::Query;
+
+/// A payee and the transactions with them
+#[derive(Serialize)]
+struct PayeeSummary<'a> {
+    payee: &'a str,
+    transactions: usize,
+    /// One amount per commodity: the matching postings' amounts if the query has account patterns, otherwise
+    /// the positive amounts of each transaction (the amount that changed hands)
+    total: Vec<Amount>,
+}
+
+pub struct PayeesReport<'j> {
+    payees: Vec<PayeeSummary<'j>>,
+}
+
+pub struct Payees {
+    query: Query,
+}
+
+impl Payees {
+    pub fn new(query: Query) -> Self {
+        Self { query }
+    }
+
+    /// Every payee with a matching transaction, in alphabetical order
+    fn summarise<'a>(&self, transactions: &'a [Transaction], postings: &[Posting]) -> Vec<PayeeSummary<'a>> {
+        let mut payees: BTreeMap<&str, (usize, BTreeMap<&str, i64>)> = BTreeMap::new();
+
+        for transaction in transactions.iter().filter(|t| self.query.matches(t, postings)) {
+            let (count, totals) = payees.entry(&transaction.payee).or_default();
+            *count += 1;
+
+            for posting in transaction.postings.iter().map(|p| &postings[*p]) {
+                let amount = match &posting.amount {
+                    Some(amount) => amount,
+                    None => continue,
+                };
+
+                let counts = match self.query.has_accounts() {
+                    true => self.query.matches_posting(posting),
+                    false => amount.quantity > 0,
+                };
+                if counts {
+                    *totals.entry(&amount.commodity).or_insert(0) += amount.quantity;
+                }
+            }
+        }
+
+        payees
+            .into_iter()
+            .map(|(payee, (transactions, totals))| PayeeSummary {
+                payee,
+                transactions,
+                total: totals
+                    .into_iter()
+                    .map(|(commodity, quantity)| Amount::new(quantity, commodity))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl Command for Payees {
+    type Report<'j> = PayeesReport<'j>;
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        PayeesReport {
+            payees: self.summarise(journal.transactions(), journal.postings()),
+        }
+    }
+}
+
+impl Report for PayeesReport<'_> {
+    fn print(&self, format: OutputFormat) {
+        let payees = &self.payees;
+
+        match format {
+            OutputFormat::Text => {
+                // Padded by display width so names with wide or combining characters line up
+                let width = payees.iter().map(|p| tree::width(p.payee)).max().unwrap_or(0);
+                for payee in payees {
+                    println!(
+                        "{}{}  {:>6}  {:>12}",
+                        payee.payee,
+                        " ".repeat(width - tree::width(payee.payee)),
+                        payee.transactions,
+                        join(&payee.total)
+                    );
+                }
+            }
+            OutputFormat::Json => print_json(&payees),
+            OutputFormat::Csv => print_csv(
+                &["payee", "transactions", "total"],
+                payees
+                    .iter()
+                    .map(|p| vec![p.payee.to_owned(), p.transactions.to_string(), join(&p.total)]),
+            ),
+            // The subcommand only accepts formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("payees has no HTML output"),
+        }
+    }
+}
+
+fn join(amounts: &[Amount]) -> String {
+    amounts.iter().map(Amount::to_string).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::{journal, query};
+
+    const JOURNAL: &str = "2020-01-01 Shop
+    Expenses:Food    £30
+    Expenses:Home    £20
+    Assets:Current
+
+2020-01-02 Shop
+    Expenses:Food    £10
+    Assets:Current
+
+2020-01-03 Cafe
+    Expenses:Food    $5
+    Assets:Cash
+";
+
+    fn totals(args: &[&str]) -> Vec<(String, usize, String)> {
+        let journal = journal(JOURNAL);
+        Payees::new(query(args))
+            .run(&journal)
+            .payees
+            .iter()
+            .map(|payee| {
+                let total: Vec<String> = payee.total.iter().map(Amount::to_string).collect();
+                (payee.payee.to_owned(), payee.transactions, total.join(", "))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_totals_the_amounts_that_changed_hands() {
+        assert_eq!(
+            totals(&[]),
+            vec![
+                ("Cafe".to_owned(), 1, "$5.00".to_owned()),
+                ("Shop".to_owned(), 2, "£60.00".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_totals_the_matching_postings_with_account_patterns() {
+        assert_eq!(
+            totals(&["Food"]),
+            vec![
+                ("Cafe".to_owned(), 1, "$5.00".to_owned()),
+                ("Shop".to_owned(), 2, "£40.00".to_owned()),
+            ]
+        );
+        assert_eq!(totals(&["Current"]), vec![("Shop".to_owned(), 2, "£-60.00".to_owned())]);
+    }
+}
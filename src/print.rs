@@ -1,25 +1,224 @@
-use reader::error::Error;
+use std::fmt::Write;
 
-use reader::reader::{Config, Reader};
+use journal::{Journal, Posting, Transaction};
+use serde::Serialize;
 
-pub struct Printer {}
+use crate::command::{Command, Report};
+use crate::output::{print_csv, print_json, OutputFormat};
+use crate::query::Query;
+
+/// What transactions are ordered by when printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Date,
+    Payee,
+    /// The total of the transaction's positive amounts
+    Amount,
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "date" => Some(SortKey::Date),
+            "payee" => Some(SortKey::Payee),
+            "amount" => Some(SortKey::Amount),
+            _ => None,
+        }
+    }
+}
+
+pub struct Printer {
+    query: Query,
+    /// Whether to show amounts which were elided in the journal
+    explicit: bool,
+    sort: SortKey,
+}
+
+/// The matching transactions, in the order they should be printed
+pub struct PrintReport<'j> {
+    transactions: Vec<&'j Transaction>,
+    postings: &'j [Posting],
+    explicit: bool,
+}
+
+/// A transaction with its postings, as serialised to JSON
+/// Amounts which were elided in the journal are always included
+#[derive(Serialize)]
+struct Entry<'a> {
+    #[serde(flatten)]
+    transaction: &'a Transaction,
+    postings: Vec<&'a Posting>,
+}
 
 impl Printer {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(query: Query, explicit: bool, sort: SortKey) -> Self {
+        Self { query, explicit, sort }
+    }
+
+    /// The transactions matching the query, in the order they should be printed
+    fn matching<'t>(&self, transactions: &'t [Transaction], postings: &[Posting]) -> Vec<&'t Transaction> {
+        let mut matching: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|t| self.query.matches(t, postings))
+            .collect();
+
+        // Sorts are stable, so transactions with equal keys stay in date then file order
+        matching.sort_by_key(|t| t.date);
+        match self.sort {
+            SortKey::Date => {}
+            SortKey::Payee => matching.sort_by(|a, b| a.payee.cmp(&b.payee)),
+            SortKey::Amount => matching.sort_by_key(|t| total(t, postings)),
+        }
+
+        matching
     }
+}
 
-    pub fn read(&mut self, file: String) -> Result<(), Error> {
-        let mut reader = Reader::new();
-        let mut config = Config::new();
-        config.should_sort = true;
+impl Command for Printer {
+    type Report<'j> = PrintReport<'j>;
 
-        let (transactions, postings, _) = reader.read(file, config)?;
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        PrintReport {
+            transactions: self.matching(journal.transactions(), journal.postings()),
+            postings: journal.postings(),
+            explicit: self.explicit,
+        }
+    }
+}
 
-        for transaction in transactions {
-            transaction.display(&postings);
+impl PrintReport<'_> {
+    /// Writes the transactions in journal format, separated by blank lines
+    pub fn write<W: Write>(&self, w: &mut W) -> std::fmt::Result {
+        for (i, transaction) in self.transactions.iter().enumerate() {
+            if i > 0 {
+                writeln!(w)?;
+            }
+            transaction.write(w, self.postings, self.explicit)?;
         }
 
         Ok(())
     }
 }
+
+impl Report for PrintReport<'_> {
+    fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => {
+                let mut output = String::new();
+                self.write(&mut output).expect("writing to a String cannot fail");
+                print!("{}", output);
+            }
+            // The subcommand only accepts formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("print has no HTML output"),
+            OutputFormat::Json => {
+                let entries: Vec<Entry> = self
+                    .transactions
+                    .iter()
+                    .map(|transaction| Entry {
+                        transaction,
+                        postings: transaction.postings.iter().map(|p| &self.postings[*p]).collect(),
+                    })
+                    .collect();
+                print_json(&entries);
+            }
+            OutputFormat::Csv => {
+                // One row per posting, with the transaction repeated on each
+                let mut rows = Vec::new();
+                for (i, transaction) in self.transactions.iter().enumerate() {
+                    for posting in transaction.postings.iter().map(|p| &self.postings[*p]) {
+                        let (commodity, quantity) = match &posting.amount {
+                            None => (String::new(), String::new()),
+                            Some(amount) => (amount.commodity.clone(), amount.quantity_string()),
+                        };
+                        rows.push(vec![
+                            (i + 1).to_string(),
+                            transaction.date.to_string(),
+                            transaction.status.to_string().trim().to_owned(),
+                            transaction.payee.clone(),
+                            posting.path.clone(),
+                            commodity,
+                            quantity,
+                        ]);
+                    }
+                }
+                print_csv(
+                    &[
+                        "transaction",
+                        "date",
+                        "status",
+                        "payee",
+                        "account",
+                        "commodity",
+                        "quantity",
+                    ],
+                    rows,
+                );
+            }
+        }
+    }
+}
+
+/// The sum of the positive amounts in a transaction
+fn total(transaction: &Transaction, postings: &[Posting]) -> i64 {
+    transaction
+        .postings
+        .iter()
+        .filter_map(|p| postings[*p].amount.as_ref())
+        .map(|a| a.quantity)
+        .filter(|q| *q > 0)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::journal;
+
+    const JOURNAL: &str = "2020-01-02 Bakery
+    Expenses:Food    £5
+    Assets:Current
+
+2020-01-01 Shop
+    Expenses:Food    £30
+    Assets:Current
+
+2020-01-03 Bakery
+    Expenses:Food    £20
+    Assets:Current
+";
+
+    /// The date and payee of each printed transaction, in order
+    fn printed(sort: SortKey) -> Vec<String> {
+        let journal = journal(JOURNAL);
+        let report = Printer::new(Query::default(), false, sort).run(&journal);
+        report
+            .transactions
+            .iter()
+            .map(|t| format!("{} {}", t.date, t.payee))
+            .collect()
+    }
+
+    #[test]
+    fn it_sorts_by_date() {
+        assert_eq!(
+            printed(SortKey::Date),
+            vec!["2020-01-01 Shop", "2020-01-02 Bakery", "2020-01-03 Bakery"]
+        );
+    }
+
+    #[test]
+    fn it_sorts_by_payee_then_date() {
+        assert_eq!(
+            printed(SortKey::Payee),
+            vec!["2020-01-02 Bakery", "2020-01-03 Bakery", "2020-01-01 Shop"]
+        );
+    }
+
+    #[test]
+    fn it_sorts_by_amount() {
+        assert_eq!(
+            printed(SortKey::Amount),
+            vec!["2020-01-02 Bakery", "2020-01-03 Bakery", "2020-01-01 Shop"]
+        );
+    }
+}
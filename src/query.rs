@@ -0,0 +1,186 @@
+use clap::{Arg, ArgMatches};
+use journal::{Date, Posting, Transaction};
+use regex::{Regex, RegexBuilder};
+use std::convert::TryFrom;
+
+/// The filters shared by reports: a date range and patterns to match accounts and payees against
+#[derive(Default)]
+pub struct Query {
+    /// Only include transactions on or after this date
+    pub begin: Option<Date>,
+    /// Only include transactions before this date
+    pub end: Option<Date>,
+    /// If there are any, postings must have an account matching one of these
+    accounts: Vec<Regex>,
+    /// If there are any, transactions must have a payee matching one of these
+    payees: Vec<Regex>,
+}
+
+impl Query {
+    /// The arguments used to build a query, for adding to a report's subcommand
+    pub fn args() -> Vec<Arg<'static, 'static>> {
+        vec![
+            Arg::with_name("begin")
+                .short("b")
+                .long("begin")
+                .value_name("DATE")
+                .help("Only include transactions on or after this date"),
+            Arg::with_name("end")
+                .short("e")
+                .long("end")
+                .value_name("DATE")
+                .help("Only include transactions before this date"),
+            Arg::with_name("period")
+                .short("p")
+                .long("period")
+                .value_name("PERIOD")
+                .conflicts_with_all(&["begin", "end"])
+                .help("Only include transactions in this period, e.g. 2020, 2020-03 or \"2020-01 to 2020-04\""),
+            Arg::with_name("query")
+                .multiple(true)
+                .value_name("QUERY")
+                .help("Only include accounts matching these patterns, or payees with payee:PATTERN"),
+        ]
+    }
+
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let mut query = Query::default();
+
+        if let Some(begin) = matches.value_of("begin") {
+            query.begin = Some(parse_date(begin)?.0);
+        }
+
+        if let Some(end) = matches.value_of("end") {
+            query.end = Some(parse_date(end)?.0);
+        }
+
+        if let Some(period) = matches.value_of("period") {
+            let (begin, end) = parse_period(period)?;
+            query.begin = begin;
+            query.end = end;
+        }
+
+        for term in matches.values_of("query").into_iter().flatten() {
+            match term.strip_prefix("payee:").or_else(|| term.strip_prefix("desc:")) {
+                Some(pattern) => query.payees.push(build_regex(pattern)?),
+                None => query.accounts.push(build_regex(term)?),
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Whether there are any account patterns to match postings against
+    pub fn has_accounts(&self) -> bool {
+        !self.accounts.is_empty()
+    }
+
+    /// Whether the transaction's date and payee match, ignoring its postings
+    pub fn matches_transaction(&self, transaction: &Transaction) -> bool {
+        if let Some(begin) = self.begin {
+            if transaction.date < begin {
+                return false;
+            }
+        }
+
+        if let Some(end) = self.end {
+            if transaction.date >= end {
+                return false;
+            }
+        }
+
+        self.payees.is_empty() || self.payees.iter().any(|p| p.is_match(&transaction.payee))
+    }
+
+    /// Whether the posting's account matches
+    pub fn matches_posting(&self, posting: &Posting) -> bool {
+        self.accounts.is_empty() || self.accounts.iter().any(|a| a.is_match(&posting.path))
+    }
+
+    /// Whether the transaction matches and has at least one matching posting
+    pub fn matches(&self, transaction: &Transaction, postings: &[Posting]) -> bool {
+        self.matches_transaction(transaction)
+            && (self.accounts.is_empty() || transaction.postings.iter().any(|p| self.matches_posting(&postings[*p])))
+    }
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern {}: {}", pattern, e))
+}
+
+/// Parses a period such as "2020", "2020-03", "from 2020-01 to 2020-04", "from 2020" or "to 2020-06-01"
+/// Returns the first day in the period and the day after it ends
+fn parse_period(period: &str) -> Result<(Option<Date>, Option<Date>), String> {
+    let period = period.trim();
+    let period = period.strip_prefix("from ").unwrap_or(period).trim();
+
+    if let Some(end) = period.strip_prefix("to ") {
+        return Ok((None, Some(parse_date(end.trim())?.0)));
+    }
+
+    match period.find(" to ") {
+        Some(i) => {
+            let begin = parse_date(period[..i].trim())?.0;
+            let end = parse_date(period[i + 4..].trim())?.0;
+            Ok((Some(begin), Some(end)))
+        }
+        None => {
+            let (begin, end) = parse_date(period)?;
+            Ok((Some(begin), Some(end)))
+        }
+    }
+}
+
+/// Parses a year, month or day such as "2020", "2020-03" or "2020/03/04"
+/// Returns the first day it covers and the first day after it
+fn parse_date(s: &str) -> Result<(Date, Date), String> {
+    let error = || format!("Invalid date {}", s);
+
+    let components: Vec<&str> = s.split(['-', '/', '.']).collect();
+    let numbers: Vec<i32> = components
+        .iter()
+        .map(|c| c.parse().map_err(|_| error()))
+        .collect::<Result<_, _>>()?;
+
+    let date = |y: i32, m: i32, d: i32| {
+        let m = u8::try_from(m).map_err(|_| error())?;
+        let d = u8::try_from(d).map_err(|_| error())?;
+        Date::try_from_ymd(y, m, d).map_err(|_| error())
+    };
+
+    match numbers.as_slice() {
+        [y] => Ok((date(*y, 1, 1)?, date(y + 1, 1, 1)?)),
+        [y, m] => {
+            let (next_y, next_m) = if *m == 12 { (y + 1, 1) } else { (*y, m + 1) };
+            Ok((date(*y, *m, 1)?, date(next_y, next_m, 1)?))
+        }
+        [y, m, d] => {
+            let day = date(*y, *m, *d)?;
+            Ok((day, day.next_day()))
+        }
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_dates() {
+        let date = |y, m, d| Date::try_from_ymd(y, m, d).unwrap();
+        assert_eq!(parse_date("2020"), Ok((date(2020, 1, 1), date(2021, 1, 1))));
+        assert_eq!(parse_date("2020/12"), Ok((date(2020, 12, 1), date(2021, 1, 1))));
+        assert_eq!(parse_date("2020-03-04"), Ok((date(2020, 3, 4), date(2020, 3, 5))));
+    }
+
+    #[test]
+    fn it_rejects_out_of_range_dates() {
+        for s in &["2020-13", "2020-257", "2020-01-257", "2020--1", "2020-01-1-1"] {
+            assert_eq!(parse_date(s), Err(format!("Invalid date {}", s)));
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use journal::{Amount, Date, Journal, Posting, Transaction};
+use reader::reader::Config;
+use serde::Serialize;
+
+use crate::command::{Command, Report};
+use crate::html;
+use crate::output::{print_csv, print_json, OutputFormat};
+use crate::query::Query;
+
+/// A matching posting with the running total of all matching postings up to and including it
+#[derive(Serialize)]
+struct Row<'a> {
+    #[serde(serialize_with = "serialize_date")]
+    date: Date,
+    payee: &'a str,
+    account: &'a str,
+    amount: Option<&'a Amount>,
+    /// One amount per commodity, in alphabetical order of commodity
+    total: Vec<Amount>,
+}
+
+pub struct Register {
+    query: Query,
+}
+
+pub struct RegisterReport<'j> {
+    rows: Vec<Row<'j>>,
+}
+
+impl Register {
+    pub fn new(query: Query) -> Self {
+        Self { query }
+    }
+
+    fn rows<'a>(&self, transactions: &'a [Transaction], postings: &'a [Posting]) -> Vec<Row<'a>> {
+        let mut totals: BTreeMap<&str, i64> = BTreeMap::new();
+        let mut rows = Vec::new();
+
+        for transaction in transactions.iter().filter(|t| self.query.matches_transaction(t)) {
+            for posting in transaction.postings.iter().map(|p| &postings[*p]) {
+                if !self.query.matches_posting(posting) {
+                    continue;
+                }
+
+                if let Some(amount) = &posting.amount {
+                    *totals.entry(&amount.commodity).or_insert(0) += amount.quantity;
+                }
+
+                rows.push(Row {
+                    date: transaction.date,
+                    payee: &transaction.payee,
+                    account: &posting.path,
+                    amount: posting.amount.as_ref(),
+                    total: totals
+                        .iter()
+                        .map(|(commodity, quantity)| Amount::new(*quantity, commodity))
+                        .collect(),
+                });
+            }
+        }
+
+        rows
+    }
+}
+
+impl Command for Register {
+    type Report<'j> = RegisterReport<'j>;
+
+    fn config(&self) -> Config {
+        let mut config = Config::new();
+        config.should_sort = true;
+        config
+    }
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        RegisterReport {
+            rows: self.rows(journal.transactions(), journal.postings()),
+        }
+    }
+}
+
+impl Report for RegisterReport<'_> {
+    fn print(&self, format: OutputFormat) {
+        let rows = &self.rows;
+
+        match format {
+            OutputFormat::Text => {
+                for row in rows {
+                    println!(
+                        "{} {} {} {:>12} {:>12}",
+                        row.date,
+                        fit(row.payee, 24),
+                        fit(row.account, 32),
+                        row.amount.map(Amount::to_string).unwrap_or_default(),
+                        join(&row.total)
+                    );
+                }
+            }
+            OutputFormat::Json => print_json(&rows),
+            OutputFormat::Csv => print_csv(
+                &["date", "payee", "account", "commodity", "quantity", "total"],
+                rows.iter().map(|row| {
+                    let (commodity, quantity) = match row.amount {
+                        None => (String::new(), String::new()),
+                        Some(amount) => (amount.commodity.clone(), amount.quantity_string()),
+                    };
+                    vec![
+                        row.date.to_string(),
+                        row.payee.to_owned(),
+                        row.account.to_owned(),
+                        commodity,
+                        quantity,
+                        join(&row.total),
+                    ]
+                }),
+            ),
+            OutputFormat::Html => {
+                let rows: Vec<Vec<String>> = rows
+                    .iter()
+                    .map(|row| {
+                        vec![
+                            html::text_cell(&row.date.to_string()),
+                            html::text_cell(row.payee),
+                            html::text_cell(row.account),
+                            html::amount_cell(&row.amount.into_iter().collect::<Vec<_>>()),
+                            html::amount_cell(&row.total.iter().collect::<Vec<_>>()),
+                        ]
+                    })
+                    .collect();
+                let body = html::table(&["Date", "Payee", "Account", "Amount", "Total"], &rows);
+                print!("{}", html::document("Register", &body));
+            }
+        }
+    }
+}
+
+/// `text` cut short or padded with spaces to fill `width` terminal columns
+fn fit(text: &str, width: usize) -> String {
+    // Prefixes are measured whole so that combining characters are counted with the character they modify
+    let end = text
+        .char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .take_while(|end| tree::width(&text[..*end]) <= width)
+        .last()
+        .unwrap_or(0);
+    let fitted = &text[..end];
+    format!("{}{}", fitted, " ".repeat(width - tree::width(fitted)))
+}
+
+fn join(amounts: &[Amount]) -> String {
+    amounts.iter().map(Amount::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn serialize_date<S: serde::Serializer>(date: &Date, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::{journal, query};
+    use crate::print::{Printer, SortKey};
+
+    #[test]
+    fn it_names_payees_as_print_does() {
+        let journal = journal(
+            "2020-01-01 AMZN Mktp UK
+    Expenses:Shopping    £80
+    Assets:Current
+
+payee Amazon
+    alias ^amzn
+",
+        );
+
+        let mut printed = String::new();
+        Printer::new(Query::default(), false, SortKey::Date)
+            .run(&journal)
+            .write(&mut printed)
+            .unwrap();
+        let report = Register::new(Query::default()).run(&journal);
+
+        assert!(printed.starts_with("2020-01-01 Amazon\n"));
+        assert!(report.rows.iter().all(|row| row.payee == "Amazon"));
+    }
+
+    #[test]
+    fn it_keeps_a_running_total_of_matching_postings_per_commodity() {
+        let journal = journal(
+            "2020-01-01 Shop
+    Expenses:Food    £30
+    Assets:Current
+
+2020-01-02 Cafe
+    Expenses:Food    $5
+    Assets:Cash
+
+2020-01-03 Shop
+    Expenses:Food    £10
+    Expenses:Home    £20
+    Assets:Current
+",
+        );
+
+        let report = Register::new(query(&["Expenses"])).run(&journal);
+        let totals: Vec<(&str, String)> = report.rows.iter().map(|row| (row.account, join(&row.total))).collect();
+        assert_eq!(
+            totals,
+            vec![
+                ("Expenses:Food", "£30.00".to_owned()),
+                ("Expenses:Food", "$5.00, £30.00".to_owned()),
+                ("Expenses:Food", "$5.00, £40.00".to_owned()),
+                ("Expenses:Home", "$5.00, £60.00".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,648 @@
+//! Financial statements: the income statement, balance sheet and cash flow reports.
+//!
+//! Account types are inferred from top-level account names (e.g. `Income` and `Revenues` are revenue accounts)
+//! and each section of a statement is made of the subtrees under the top-level accounts of its types. Cash flow
+//! only includes the asset accounts which `CASH_ACCOUNTS` matches.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use journal::{Amount, Date, Journal, Posting, Transaction};
+use regex::Regex;
+use serde::Serialize;
+use tree::Tree;
+
+use crate::command::{Command, Report};
+use crate::output::{print_csv, print_json, OutputFormat};
+
+/// Matches the names of the accounts whose changes are shown in the cash flow report
+/// The cashflow subcommand's help lists these words, so keep the two in step
+const CASH_ACCOUNTS: &str = "(?i)cash|bank|checking|current|saving";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountType {
+    Asset,
+    Liability,
+    Equity,
+    Revenue,
+    Expense,
+}
+
+impl AccountType {
+    /// Infers the type of an account from its top-level name
+    pub fn of(top_level: &str) -> Option<Self> {
+        match top_level.to_lowercase().as_str() {
+            "asset" | "assets" => Some(AccountType::Asset),
+            "liability" | "liabilities" => Some(AccountType::Liability),
+            "equity" => Some(AccountType::Equity),
+            "income" | "revenue" | "revenues" => Some(AccountType::Revenue),
+            "expense" | "expenses" => Some(AccountType::Expense),
+            _ => None,
+        }
+    }
+
+    /// Whether balances of this type are normally negative, and so are negated in statements
+    fn is_credit(self) -> bool {
+        matches!(
+            self,
+            AccountType::Liability | AccountType::Equity | AccountType::Revenue
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatementKind {
+    IncomeStatement,
+    BalanceSheet,
+    CashFlow,
+}
+
+impl StatementKind {
+    fn title(self) -> &'static str {
+        match self {
+            StatementKind::IncomeStatement => "Income Statement",
+            StatementKind::BalanceSheet => "Balance Sheet",
+            StatementKind::CashFlow => "Cash Flow",
+        }
+    }
+
+    /// The titles of the sections and the account types in each
+    fn sections(self) -> Vec<(&'static str, Vec<AccountType>)> {
+        match self {
+            StatementKind::IncomeStatement => vec![
+                ("Revenues", vec![AccountType::Revenue]),
+                ("Expenses", vec![AccountType::Expense]),
+            ],
+            StatementKind::BalanceSheet => vec![
+                ("Assets", vec![AccountType::Asset]),
+                ("Liabilities", vec![AccountType::Liability]),
+                ("Equity", vec![AccountType::Equity]),
+            ],
+            StatementKind::CashFlow => vec![("Cash flows", vec![AccountType::Asset])],
+        }
+    }
+
+    fn net_title(self) -> &'static str {
+        match self {
+            StatementKind::IncomeStatement => "Net income",
+            StatementKind::BalanceSheet => "Net worth",
+            StatementKind::CashFlow => "Net cash flow",
+        }
+    }
+
+    /// How an account's balance contributes to the net line
+    /// Net income is revenues less expenses, net worth is assets less liabilities and net cash flow is the
+    /// change in cash
+    fn net_sign(self, account_type: AccountType) -> i64 {
+        match (self, account_type) {
+            (StatementKind::IncomeStatement, _) => -1,
+            (StatementKind::BalanceSheet, AccountType::Equity) => 0,
+            _ => 1,
+        }
+    }
+
+    /// Balance sheets show balances at the end of each period rather than changes during it
+    fn is_cumulative(self) -> bool {
+        self == StatementKind::BalanceSheet
+    }
+}
+
+/// The length of the periods shown as columns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Interval {
+    /// The first day of the period containing `date`
+    fn start_of(self, date: Date) -> Date {
+        let month = match self {
+            Interval::Monthly => date.month(),
+            Interval::Quarterly => (date.month() - 1) / 3 * 3 + 1,
+            Interval::Yearly => 1,
+        };
+        Date::try_from_ymd(date.year(), month, 1).unwrap()
+    }
+
+    /// The first day of the period after the one starting on `start`
+    fn next(self, start: Date) -> Date {
+        let months = match self {
+            Interval::Monthly => 1,
+            Interval::Quarterly => 3,
+            Interval::Yearly => 12,
+        };
+        let month = start.month() as i32 - 1 + months;
+        Date::try_from_ymd(start.year() + month / 12, (month % 12) as u8 + 1, 1).unwrap()
+    }
+
+    fn label(self, start: Date) -> String {
+        match self {
+            Interval::Monthly => start.format("%Y-%m"),
+            Interval::Quarterly => format!("{}Q{}", start.year(), (start.month() - 1) / 3 + 1),
+            Interval::Yearly => start.year().to_string(),
+        }
+    }
+}
+
+/// An account's quantity of each commodity in each column
+#[derive(Default)]
+struct Columns(BTreeMap<String, Vec<i64>>);
+
+impl Columns {
+    fn add(&mut self, commodity: &str, column: usize, quantity: i64, width: usize) {
+        let quantities = self.0.entry(commodity.to_owned()).or_default();
+        quantities.resize(width, 0);
+        quantities[column] += quantity;
+    }
+
+    /// Adds `sign` times each of `other`'s quantities
+    fn add_columns(&mut self, other: &Columns, sign: i64, width: usize) {
+        for (commodity, quantities) in &other.0 {
+            for (column, quantity) in quantities.iter().enumerate() {
+                self.add(commodity, column, quantity * sign, width);
+            }
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.values().flatten().all(|q| *q == 0)
+    }
+
+    /// A line for each commodity which isn't zero in every column, with quantities multiplied by `sign`
+    /// If there are none, this is a single line of zeros
+    fn lines(&self, sign: i64, width: usize) -> Vec<Line> {
+        let lines: Vec<Line> = self
+            .0
+            .iter()
+            .filter(|(_, quantities)| quantities.iter().any(|q| *q != 0))
+            .map(|(commodity, quantities)| Line {
+                commodity: commodity.clone(),
+                amounts: (0..width)
+                    .map(|i| Amount::new(quantities.get(i).copied().unwrap_or(0) * sign, commodity))
+                    .collect(),
+            })
+            .collect();
+
+        match lines.is_empty() {
+            false => lines,
+            true => vec![Line {
+                commodity: String::new(),
+                amounts: (0..width).map(|_| Amount::new(0, "")).collect(),
+            }],
+        }
+    }
+}
+
+/// The amounts of one commodity in each column
+#[derive(Serialize)]
+struct Line {
+    commodity: String,
+    amounts: Vec<Amount>,
+}
+
+/// An account as it is shown in a section, with a row for each commodity
+#[derive(Serialize)]
+struct Row {
+    account: String,
+    #[serde(skip)]
+    name: String,
+    #[serde(skip)]
+    depth: usize,
+    #[serde(flatten)]
+    line: Line,
+}
+
+#[derive(Serialize)]
+struct Section {
+    title: &'static str,
+    accounts: Vec<Row>,
+    total: Vec<Line>,
+}
+
+/// The statement as serialised to JSON
+#[derive(Serialize)]
+pub struct StatementReport {
+    title: &'static str,
+    columns: Vec<String>,
+    sections: Vec<Section>,
+    net_title: &'static str,
+    net: Vec<Line>,
+}
+
+pub struct Statement {
+    kind: StatementKind,
+    interval: Option<Interval>,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, interval: Option<Interval>) -> Self {
+        Self { kind, interval }
+    }
+
+    fn report(&self, transactions: &[Transaction], postings: &[Posting]) -> StatementReport {
+        let starts = self.column_starts(transactions);
+        let width = starts.len();
+
+        let cash = Regex::new(CASH_ACCOUNTS).unwrap();
+
+        let mut tree: Tree<Columns> = Tree::new();
+        for posting in postings {
+            let path: Vec<&str> = posting.path.split(':').collect();
+            if AccountType::of(path[0]).is_none() {
+                continue;
+            }
+            if self.kind == StatementKind::CashFlow && !cash.is_match(&posting.path) {
+                continue;
+            }
+            let amount = match &posting.amount {
+                Some(amount) => amount,
+                None => continue,
+            };
+
+            let date = posting.transaction.map(|t| transactions[t].date);
+            let column = match (&self.interval, date) {
+                (Some(_), Some(date)) => starts.partition_point(|start| *start <= date).saturating_sub(1),
+                _ => 0,
+            };
+
+            let index = tree.add_path(&path);
+            let columns = &mut tree.get_node_at_index_mut(index).unwrap().value;
+            columns.add(&amount.commodity, column, amount.quantity, width);
+        }
+        tree.aggregate(|total, columns| total.add_columns(columns, 1, width));
+
+        if self.kind.is_cumulative() {
+            // Turn changes in each period into balances at the end of each period
+            tree.walk_descendants(tree.root(), |node| {
+                for quantities in node.value.0.values_mut() {
+                    let mut balance = 0;
+                    for quantity in quantities.iter_mut() {
+                        balance += *quantity;
+                        *quantity = balance;
+                    }
+                }
+            });
+        }
+
+        let mut net = Columns::default();
+        let mut sections = Vec::new();
+
+        for (title, types) in self.kind.sections() {
+            let mut accounts = Vec::new();
+            let mut total = Columns::default();
+
+            for (name, index) in tree.children(tree.root()) {
+                let account_type = match AccountType::of(name) {
+                    Some(t) if types.contains(&t) => t,
+                    _ => continue,
+                };
+                let sign = if account_type.is_credit() { -1 } else { 1 };
+
+                let mut path = vec![name];
+                add_rows(&tree, index, &mut path, &mut accounts, &|columns| {
+                    columns.lines(sign, width)
+                });
+
+                let columns = &tree.get_node_at_index(index).unwrap().value;
+                total.add_columns(columns, sign, width);
+                net.add_columns(columns, self.kind.net_sign(account_type), width);
+            }
+
+            sections.push(Section {
+                title,
+                accounts,
+                total: total.lines(1, width),
+            });
+        }
+
+        let columns = match self.interval {
+            None => vec![match self.kind.is_cumulative() {
+                true => "Balance".to_owned(),
+                false => "Total".to_owned(),
+            }],
+            Some(interval) => starts.iter().map(|s| interval.label(*s)).collect(),
+        };
+
+        StatementReport {
+            title: self.kind.title(),
+            columns,
+            sections,
+            net_title: self.kind.net_title(),
+            net: net.lines(1, width),
+        }
+    }
+
+    /// The first day of each column's period, or a single column if there's no interval
+    fn column_starts(&self, transactions: &[Transaction]) -> Vec<Date> {
+        let first = transactions.iter().map(|t| t.date).min();
+        let last = transactions.iter().map(|t| t.date).max();
+
+        match (self.interval, first, last) {
+            (Some(interval), Some(first), Some(last)) => {
+                let mut starts = vec![interval.start_of(first)];
+                loop {
+                    let next = interval.next(*starts.last().unwrap());
+                    if next > last {
+                        break;
+                    }
+                    starts.push(next);
+                }
+                starts
+            }
+            _ => vec![first.unwrap_or_else(|| Date::try_from_ymd(1970, 1, 1).unwrap())],
+        }
+    }
+}
+
+/// Adds rows for the node at `index` and its descendants, leaving out accounts which are zero in every column
+fn add_rows<'t, F>(tree: &'t Tree<Columns>, index: usize, path: &mut Vec<&'t str>, rows: &mut Vec<Row>, lines: &F)
+where
+    F: Fn(&Columns) -> Vec<Line>,
+{
+    let columns = &tree.get_node_at_index(index).unwrap().value;
+    if columns.is_zero() && tree.children(index).is_empty() {
+        return;
+    }
+
+    for line in lines(columns) {
+        rows.push(Row {
+            account: path.join(":"),
+            name: path.last().unwrap().to_string(),
+            depth: path.len() - 1,
+            line,
+        });
+    }
+
+    for (name, child) in tree.children(index) {
+        path.push(name);
+        add_rows(tree, child, path, rows, lines);
+        path.pop();
+    }
+}
+
+fn record(section: &str, account: &str, line: &Line) -> Vec<String> {
+    let mut record = vec![section.to_owned(), account.to_owned(), line.commodity.clone()];
+    record.extend(line.amounts.iter().map(Amount::quantity_string));
+    record
+}
+
+impl Command for Statement {
+    type Report<'j> = StatementReport;
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        self.report(journal.transactions(), journal.postings())
+    }
+}
+
+impl StatementReport {
+    /// A CSV record for each row, then each total and the net line, with one for each commodity
+    fn records(&self) -> Vec<Vec<String>> {
+        let mut records = Vec::new();
+        for section in &self.sections {
+            for row in &section.accounts {
+                records.push(record(section.title, &row.account, &row.line));
+            }
+            for line in &section.total {
+                records.push(record(section.title, "Total", line));
+            }
+        }
+        for line in &self.net {
+            records.push(record(self.title, self.net_title, line));
+        }
+        records
+    }
+}
+
+impl Report for StatementReport {
+    fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => {
+                let stdout = io::stdout();
+                write_text(self, &mut stdout.lock()).expect("failed to write to stdout");
+            }
+            OutputFormat::Json => print_json(self),
+            OutputFormat::Csv => {
+                let mut header = vec!["section".to_owned(), "account".to_owned(), "commodity".to_owned()];
+                header.extend(self.columns.iter().cloned());
+                let header: Vec<&str> = header.iter().map(String::as_str).collect();
+
+                print_csv(&header, self.records());
+            }
+            // The subcommands only accept formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("statements have no HTML output"),
+        }
+    }
+}
+
+/// Writes the statement as a table with a column for each period
+fn write_text<W: io::Write>(report: &StatementReport, w: &mut W) -> io::Result<()> {
+    const NAME_WIDTH: usize = 32;
+    const COLUMN_WIDTH: usize = 14;
+
+    // A line with no commodity is the zeros of a section with no accounts, shown as 0 as in balance
+    let columns = |line: &Line| -> String {
+        line.amounts
+            .iter()
+            .map(|a| match line.commodity.is_empty() && a.quantity == 0 {
+                true => format!("{:>width$}", 0, width = COLUMN_WIDTH),
+                false => format!("{:>width$}", a.to_string(), width = COLUMN_WIDTH),
+            })
+            .collect()
+    };
+    let rule = "-".repeat(NAME_WIDTH + COLUMN_WIDTH * report.columns.len());
+
+    write!(w, "{:<width$}", report.title, width = NAME_WIDTH)?;
+    for column in &report.columns {
+        write!(w, "{:>width$}", column, width = COLUMN_WIDTH)?;
+    }
+    writeln!(w)?;
+    writeln!(w, "{}", rule)?;
+
+    for section in &report.sections {
+        writeln!(w, "{}", section.title)?;
+        for (i, row) in section.accounts.iter().enumerate() {
+            // An account's name is only on the first of its commodities' rows
+            let name = match i > 0 && section.accounts[i - 1].account == row.account {
+                true => String::new(),
+                false => format!("{:indent$}{}", "", row.name, indent = row.depth * 2 + 2),
+            };
+            writeln!(w, "{:<width$}{}", name, columns(&row.line), width = NAME_WIDTH)?;
+        }
+        writeln!(
+            w,
+            "{:<width$}{}",
+            "",
+            "-".repeat(COLUMN_WIDTH * report.columns.len()),
+            width = NAME_WIDTH
+        )?;
+        for line in &section.total {
+            writeln!(w, "{:<width$}{}", "", columns(line), width = NAME_WIDTH)?;
+        }
+        writeln!(w)?;
+    }
+
+    writeln!(w, "{}", rule)?;
+    for (i, line) in report.net.iter().enumerate() {
+        let title = if i == 0 { report.net_title } else { "" };
+        writeln!(w, "{:<width$}{}", title, columns(line), width = NAME_WIDTH)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::journal;
+
+    const JOURNAL: &str = "2020-01-15 Salary
+    Assets:Bank    £1000
+    Income:Salary
+
+2020-02-10 Consulting
+    Assets:Bank    $500
+    Income:Consulting
+
+2020-04-05 Hotel
+    Expenses:Travel    $200
+    Assets:Bank
+
+2020-04-06 Shop
+    Expenses:Food    £30
+    Liabilities:Card
+
+2021-01-02 Salary
+    Assets:Bank    £1000
+    Income:Salary
+";
+
+    fn records(kind: StatementKind, interval: Option<Interval>) -> Vec<String> {
+        let journal = journal(JOURNAL);
+        let report = Statement::new(kind, interval).run(&journal);
+        report.records().iter().map(|record| record.join(",")).collect()
+    }
+
+    #[test]
+    fn it_totals_the_income_statement_in_each_commodity() {
+        assert_eq!(
+            records(StatementKind::IncomeStatement, None),
+            vec![
+                "Revenues,Income,$,500.00",
+                "Revenues,Income,£,2000.00",
+                "Revenues,Income:Consulting,$,500.00",
+                "Revenues,Income:Salary,£,2000.00",
+                "Revenues,Total,$,500.00",
+                "Revenues,Total,£,2000.00",
+                "Expenses,Expenses,$,200.00",
+                "Expenses,Expenses,£,30.00",
+                "Expenses,Expenses:Food,£,30.00",
+                "Expenses,Expenses:Travel,$,200.00",
+                "Expenses,Total,$,200.00",
+                "Expenses,Total,£,30.00",
+                "Income Statement,Net income,$,300.00",
+                "Income Statement,Net income,£,1970.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_shows_quarterly_columns() {
+        let journal = journal(JOURNAL);
+        let report = Statement::new(StatementKind::IncomeStatement, Some(Interval::Quarterly)).run(&journal);
+        assert_eq!(report.columns, vec!["2020Q1", "2020Q2", "2020Q3", "2020Q4", "2021Q1"]);
+
+        let records = records(StatementKind::IncomeStatement, Some(Interval::Quarterly));
+        assert_eq!(
+            &records[records.len() - 2..],
+            &[
+                "Income Statement,Net income,$,500.00,-200.00,0.00,0.00,0.00",
+                "Income Statement,Net income,£,1000.00,-30.00,0.00,0.00,1000.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_shows_yearly_balances_in_each_commodity() {
+        assert_eq!(
+            records(StatementKind::BalanceSheet, Some(Interval::Yearly)),
+            vec![
+                "Assets,Assets,$,300.00,300.00",
+                "Assets,Assets,£,1000.00,2000.00",
+                "Assets,Assets:Bank,$,300.00,300.00",
+                "Assets,Assets:Bank,£,1000.00,2000.00",
+                "Assets,Total,$,300.00,300.00",
+                "Assets,Total,£,1000.00,2000.00",
+                "Liabilities,Liabilities,£,30.00,30.00",
+                "Liabilities,Liabilities:Card,£,30.00,30.00",
+                "Liabilities,Total,£,30.00,30.00",
+                "Equity,Total,,0.00,0.00",
+                "Balance Sheet,Net worth,$,300.00,300.00",
+                "Balance Sheet,Net worth,£,970.00,1970.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_shows_monthly_cash_flows_in_each_commodity() {
+        let journal = journal(JOURNAL);
+        let report = Statement::new(StatementKind::CashFlow, Some(Interval::Monthly)).run(&journal);
+        assert_eq!(report.columns.len(), 13);
+        assert_eq!(report.columns[0], "2020-01");
+        assert_eq!(report.columns[12], "2021-01");
+
+        let net: Vec<(&str, Vec<i64>)> = report
+            .net
+            .iter()
+            .map(|line| {
+                let quantities = line.amounts.iter().map(|a| a.quantity / 100).collect();
+                (line.commodity.as_str(), quantities)
+            })
+            .collect();
+        assert_eq!(
+            net,
+            vec![
+                ("$", vec![0, 500, 0, -200, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                ("£", vec![1000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1000]),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_writes_each_commodity_on_its_own_line() {
+        let journal = journal(JOURNAL);
+        let report = Statement::new(StatementKind::BalanceSheet, None).run(&journal);
+        let mut output = Vec::new();
+        write_text(&report, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Balance Sheet                          Balance
+----------------------------------------------
+Assets
+  Assets                               $300.00
+                                      £2000.00
+    Bank                               $300.00
+                                      £2000.00
+                                --------------
+                                       $300.00
+                                      £2000.00
+
+Liabilities
+  Liabilities                           £30.00
+    Card                                £30.00
+                                --------------
+                                        £30.00
+
+Equity
+                                --------------
+                                             0
+
+----------------------------------------------
+Net worth                              $300.00
+                                      £1970.00
+"
+        );
+    }
+}
@@ -1,89 +1,427 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::path::PathBuf;
 
-use journal::Transaction;
+use journal::{Amount, Status};
 use reader::error::Error;
+use reader::stream::Stream;
 use reader::Date;
 
-use reader::reader::{Config, Reader};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::command::{Report, StreamCommand};
+use crate::output::{print_csv, print_json, OutputFormat};
 
 pub struct Statistics {
-    start_date: Date,
-    end_date: Date,
-    sources: HashSet<PathBuf>,
-    transaction_count: usize,
-    posting_count: usize,
-    unique_accounts: HashSet<String>,
-    unique_payees: HashSet<String>,
+    /// The files passed to rledger, in order
+    roots: Vec<String>,
+    /// The recent transaction counts are relative to this
+    today: Date,
+}
+
+/// The statistics as serialised to JSON
+#[derive(Serialize)]
+pub struct Summary {
+    files: Vec<String>,
+    sources: Vec<SourceSummary>,
+    /// None if the journal has no transactions
+    first_transaction: Option<String>,
+    last_transaction: Option<String>,
+    /// The number of days from the first transaction to the last, including both
+    days: i64,
+    transactions: usize,
+    transactions_per_day: f64,
+    transactions_last_30_days: usize,
+    transactions_last_7_days: usize,
+    cleared: usize,
+    pending: usize,
+    unmarked: usize,
+    /// The largest transaction in each commodity, in order of commodity
+    largest_transactions: Vec<LargestTransaction>,
+    postings: usize,
+    unique_accounts: usize,
+    /// How many accounts, including parents which have no postings of their own, are at each depth from 1
+    accounts_by_depth: BTreeMap<usize, usize>,
+    unique_payees: usize,
+    commodities: BTreeSet<String>,
+    /// How long reading and summarising the journal took, from when its files were opened
+    run_time_ms: f64,
+}
+
+#[derive(Serialize)]
+struct SourceSummary {
+    path: PathBuf,
+    transactions: usize,
+}
+
+/// The transaction with the largest single posting in a commodity
+#[derive(Serialize)]
+struct LargestTransaction {
+    date: String,
+    payee: String,
+    amount: Amount,
 }
 
 impl Statistics {
-    pub fn new() -> Self {
+    /// `roots` are the files passed to rledger
+    pub fn new(roots: Vec<String>) -> Self {
         Self {
-            start_date: Date::try_from_ymd(100000, 1, 1).unwrap(),
-            end_date: Date::try_from_ymd(-100000, 1, 1).unwrap(),
-            sources: HashSet::new(),
-            transaction_count: 0,
-            posting_count: 0,
-            unique_accounts: HashSet::new(),
-            unique_payees: HashSet::new(),
+            roots,
+            today: OffsetDateTime::try_now_local()
+                .unwrap_or_else(|_| OffsetDateTime::now_utc())
+                .date(),
         }
     }
 
-    pub fn read(&mut self, file: String) -> Result<(), Error> {
-        let mut reader = Reader::new();
-        let config = Config::new();
+    /// Whether `date` is in the `days` days up to and including today
+    fn is_recent(&self, date: Date, days: i64) -> bool {
+        date >= self.today - time::Duration::days(days - 1) && date <= self.today
+    }
+}
 
-        let (transactions, postings, sources) = reader.read(file, config)?;
+impl StreamCommand for Statistics {
+    type Report = Summary;
 
-        self.sources = sources;
+    fn run(&self, stream: Stream) -> Result<Self::Report, Error> {
+        // Payees are renamed once everything is read, as aliases can come after the transactions they apply to
+        let mut stream = stream.without_renaming();
+        let mut first: Option<Date> = None;
+        let mut last: Option<Date> = None;
+        let mut transactions = 0;
+        let (mut transactions_last_30_days, mut transactions_last_7_days) = (0, 0);
+        let (mut cleared, mut pending, mut unmarked) = (0, 0, 0);
+        let mut largest_transactions: BTreeMap<String, LargestTransaction> = BTreeMap::new();
+        let mut postings = 0;
+        let mut unique_accounts = HashSet::new();
+        let mut payees = HashSet::new();
+        let mut commodities = BTreeSet::new();
 
-        for t in transactions {
-            self.process_transaction(&t);
-            self.unique_payees.insert(t.payee);
-        }
+        for entry in &mut stream {
+            let (transaction, transaction_postings) = entry?;
+
+            let date = transaction.date;
+            first = Some(first.map_or(date, |first| first.min(date)));
+            last = Some(last.map_or(date, |last| last.max(date)));
+            transactions += 1;
+            if self.is_recent(date, 30) {
+                transactions_last_30_days += 1;
+            }
+            if self.is_recent(date, 7) {
+                transactions_last_7_days += 1;
+            }
+            match transaction.status {
+                Status::Cleared => cleared += 1,
+                Status::Uncleared => pending += 1,
+                Status::NoStatus => unmarked += 1,
+            }
 
-        for p in postings {
-            self.posting_count += 1;
-            self.unique_accounts.insert(p.path);
+            // Amounts are only compared with others in the same commodity, and only a strictly larger amount
+            // replaces the largest so far, so the first of equals is kept
+            for amount in transaction_postings.iter().filter_map(|p| p.amount.as_ref()) {
+                let largest = largest_transactions.get(&amount.commodity);
+                if largest.is_none_or(|largest| amount.quantity > largest.amount.quantity) {
+                    let largest = LargestTransaction {
+                        date: date.to_string(),
+                        payee: transaction.payee.clone(),
+                        amount: amount.clone(),
+                    };
+                    largest_transactions.insert(amount.commodity.clone(), largest);
+                }
+            }
+
+            postings += transaction_postings.len();
+            for posting in transaction_postings {
+                if let Some(amount) = posting.amount {
+                    if !amount.commodity.is_empty() {
+                        commodities.insert(amount.commodity);
+                    }
+                }
+                unique_accounts.insert(posting.path);
+            }
+            payees.insert(transaction.payee);
         }
 
-        self.report();
+        let days = match (first, last) {
+            (Some(first), Some(last)) => (last - first).whole_days() + 1,
+            _ => 0,
+        };
+        let transactions_per_day = match days {
+            0 => 0.,
+            days => transactions as f64 / days as f64,
+        };
+
+        let mut sources: Vec<SourceSummary> = stream
+            .sources()
+            .iter()
+            .map(|path| SourceSummary {
+                path: path.clone(),
+                transactions: stream.transaction_counts().get(path).copied().unwrap_or(0),
+            })
+            .collect();
+        sources.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut accounts_by_depth = BTreeMap::new();
+        let all_accounts: HashSet<&str> = unique_accounts
+            .iter()
+            .flat_map(|account| {
+                account
+                    .match_indices(':')
+                    .map(move |(i, _)| &account[..i])
+                    .chain(Some(account.as_str()))
+            })
+            .collect();
+        for account in all_accounts {
+            *accounts_by_depth.entry(account.split(':').count()).or_insert(0) += 1;
+        }
 
-        Ok(())
+        Ok(Summary {
+            files: self.roots.clone(),
+            sources,
+            first_transaction: first.map(|d| d.to_string()),
+            last_transaction: last.map(|d| d.to_string()),
+            days,
+            transactions,
+            transactions_per_day,
+            transactions_last_30_days,
+            transactions_last_7_days,
+            cleared,
+            pending,
+            unmarked,
+            largest_transactions: largest_transactions
+                .into_values()
+                .map(|mut largest| {
+                    if let Some(name) = stream.alias_of(&largest.payee) {
+                        largest.payee = name.to_owned();
+                    }
+                    largest
+                })
+                .collect(),
+            postings,
+            unique_accounts: unique_accounts.len(),
+            accounts_by_depth,
+            unique_payees: payees
+                .iter()
+                .map(|payee| stream.alias_of(payee).unwrap_or(payee))
+                .collect::<HashSet<&str>>()
+                .len(),
+            commodities,
+            run_time_ms: stream.elapsed().as_secs_f64() * 1000.,
+        })
     }
+}
 
-    fn process_transaction(&mut self, transaction: &Transaction) {
-        self.transaction_count += 1;
+impl Report for Summary {
+    fn print(&self, format: OutputFormat) {
+        let summary = self;
 
-        if transaction.date.lt(&self.start_date) {
-            self.start_date = transaction.date;
+        match format {
+            OutputFormat::Text => report_text(summary),
+            // The subcommand only accepts formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("statistics has no HTML output"),
+            OutputFormat::Json => print_json(summary),
+            OutputFormat::Csv => {
+                let mut rows = vec![
+                    ["files".to_owned(), summary.files.join(";")],
+                    [
+                        "sources".to_owned(),
+                        summary
+                            .sources
+                            .iter()
+                            .map(|s| s.path.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join(";"),
+                    ],
+                ];
+                for source in &summary.sources {
+                    rows.push([
+                        format!("transactions_in:{}", source.path.to_string_lossy()),
+                        source.transactions.to_string(),
+                    ]);
+                }
+                rows.extend(vec![
+                    [
+                        "first_transaction".to_owned(),
+                        summary.first_transaction.clone().unwrap_or_default(),
+                    ],
+                    [
+                        "last_transaction".to_owned(),
+                        summary.last_transaction.clone().unwrap_or_default(),
+                    ],
+                    ["days".to_owned(), summary.days.to_string()],
+                    ["transactions".to_owned(), summary.transactions.to_string()],
+                    [
+                        "transactions_per_day".to_owned(),
+                        summary.transactions_per_day.to_string(),
+                    ],
+                    [
+                        "transactions_last_30_days".to_owned(),
+                        summary.transactions_last_30_days.to_string(),
+                    ],
+                    [
+                        "transactions_last_7_days".to_owned(),
+                        summary.transactions_last_7_days.to_string(),
+                    ],
+                    ["cleared".to_owned(), summary.cleared.to_string()],
+                    ["pending".to_owned(), summary.pending.to_string()],
+                    ["unmarked".to_owned(), summary.unmarked.to_string()],
+                ]);
+                for largest in &summary.largest_transactions {
+                    rows.push([
+                        format!("largest_transaction:{}", largest.amount.commodity),
+                        format!("{} {} {}", largest.date, largest.payee, largest.amount),
+                    ]);
+                }
+                rows.extend(vec![
+                    ["postings".to_owned(), summary.postings.to_string()],
+                    ["unique_accounts".to_owned(), summary.unique_accounts.to_string()],
+                ]);
+                for (depth, count) in &summary.accounts_by_depth {
+                    rows.push([format!("accounts_at_depth:{}", depth), count.to_string()]);
+                }
+                rows.extend(vec![
+                    ["unique_payees".to_owned(), summary.unique_payees.to_string()],
+                    [
+                        "commodities".to_owned(),
+                        summary.commodities.iter().cloned().collect::<Vec<_>>().join(";"),
+                    ],
+                    ["run_time_ms".to_owned(), summary.run_time_ms.to_string()],
+                ]);
+                print_csv(&["statistic", "value"], rows)
+            }
         }
+    }
+}
 
-        if transaction.date.gt(&self.end_date) {
-            self.end_date = transaction.date;
+fn report_text(summary: &Summary) {
+    println!("Journal files:");
+    for root in &summary.files {
+        println!("  {}", root)
+    }
+    println!("Transactions found in {} files", summary.sources.len());
+    for s in &summary.sources {
+        println!("  {} ({})", s.path.display(), s.transactions)
+    }
+
+    match (&summary.first_transaction, &summary.last_transaction) {
+        (Some(first), Some(last)) => {
+            println!("First transaction:\t{}", first);
+            println!("Last transaction:\t{}", last);
+            println!("Time period:\t\t{} days", summary.days);
+        }
+        _ => println!("No transactions"),
+    }
+    println!(
+        "Transactions:\t\t{} ({:.1} per day)",
+        summary.transactions, summary.transactions_per_day
+    );
+    println!("  Last 30 days:\t\t{}", summary.transactions_last_30_days);
+    println!("  Last 7 days:\t\t{}", summary.transactions_last_7_days);
+    println!("  Cleared:\t\t{}", summary.cleared);
+    println!("  Pending:\t\t{}", summary.pending);
+    println!("  Unmarked:\t\t{}", summary.unmarked);
+    if !summary.largest_transactions.is_empty() {
+        println!("Largest transactions:");
+        for largest in &summary.largest_transactions {
+            println!("  {}\t\t{} {}", largest.amount, largest.date, largest.payee);
         }
     }
+    println!("Postings:\t\t{}", summary.postings);
+    println!("Unique accounts:\t{}", summary.unique_accounts);
+    for (depth, count) in &summary.accounts_by_depth {
+        println!("  At depth {}:\t\t{}", depth, count);
+    }
+    println!("Unique payees:\t\t{}", summary.unique_payees);
+    println!(
+        "Commodities:\t\t{}",
+        summary.commodities.iter().cloned().collect::<Vec<_>>().join(", ")
+    );
+    println!("Run time:\t\t{:.1} ms", summary.run_time_ms);
+}
 
-    fn report(&self) {
-        let days = (self.end_date - self.start_date).as_seconds_f64() / 60. / 60. / 24.;
-        let txs_per_day = (self.transaction_count as f64) / days;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
 
-        println!("Transactions found in {} files", self.sources.len());
-        let mut sources: Vec<&PathBuf> = self.sources.iter().collect();
-        sources.sort();
-        for s in sources {
-            println!("  {:?}", s)
-        }
-        println!("First transaction:\t{}", self.start_date);
-        println!("Last transaction:\t{}", self.end_date);
-        println!("Time period:\t\t{:.0} days", days);
-        println!(
-            "Transactions:\t\t{} ({:.1} per day)",
-            self.transaction_count, txs_per_day
+    fn summary(contents: &str) -> Summary {
+        let stream = Stream::from_reader("<string>", io::Cursor::new(contents.to_owned()), false);
+        Statistics::new(Vec::new()).run(stream).unwrap()
+    }
+
+    #[test]
+    fn it_finds_the_largest_transaction_in_each_commodity() {
+        let summary = summary(
+            "2020-01-01 Hotel
+    Expenses:Travel    ¥100
+    Assets:Yen
+
+2020-01-02 Shop
+    Expenses:Food    £50
+    Assets:Current
+
+2020-01-03 Cafe
+    Expenses:Food    £50
+    Assets:Current
+",
+        );
+
+        let largest: Vec<String> = summary
+            .largest_transactions
+            .iter()
+            .map(|t| format!("{} {} {}", t.date, t.payee, t.amount))
+            .collect();
+        assert_eq!(largest, vec!["2020-01-02 Shop £50.00", "2020-01-01 Hotel ¥100.00"]);
+    }
+
+    #[test]
+    fn it_renames_payees_with_aliases_declared_after_them() {
+        let summary = summary(
+            "2020-01-01 AMZN Mktp UK
+    Expenses:Shopping    £80
+    Assets:Current
+
+2020-01-02 Amazon
+    Expenses:Shopping    £10
+    Assets:Current
+
+payee Amazon
+    alias ^amzn
+",
+        );
+
+        assert_eq!(summary.unique_payees, 1);
+        assert_eq!(summary.largest_transactions[0].payee, "Amazon");
+    }
+
+    #[test]
+    fn it_counts_transactions_postings_and_accounts() {
+        let summary = summary(
+            "2020-01-01 * Shop
+    Expenses:Food:Groceries    £30
+    Assets:Current
+
+2020-01-05 ! Cafe
+    Expenses:Food    $5
+    Assets:Cash
+
+2020-01-10 Shop
+    Expenses:Home    £20
+    Assets:Current
+",
+        );
+
+        assert_eq!(summary.first_transaction.as_deref(), Some("2020-01-01"));
+        assert_eq!(summary.last_transaction.as_deref(), Some("2020-01-10"));
+        assert_eq!(summary.days, 10);
+        assert_eq!(summary.transactions, 3);
+        assert_eq!((summary.cleared, summary.pending, summary.unmarked), (1, 1, 1));
+        assert_eq!(summary.postings, 6);
+        assert_eq!(summary.unique_accounts, 5);
+        assert_eq!(
+            summary.accounts_by_depth.into_iter().collect::<Vec<_>>(),
+            vec![(1, 2), (2, 4), (3, 1)]
         );
-        println!("Postings:\t\t{}", self.posting_count);
-        println!("Unique accounts:\t{}", self.unique_accounts.len());
-        println!("Unique payees:\t\t{}", self.unique_payees.len());
+        assert_eq!(summary.unique_payees, 2);
+        assert_eq!(summary.commodities.into_iter().collect::<Vec<_>>(), vec!["$", "£"]);
     }
 }
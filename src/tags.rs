@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use journal::{Journal, Posting, Tag, Transaction};
+use serde::Serialize;
+
+use crate::command::{Command, Report};
+use crate::output::{print_csv, print_json, OutputFormat};
+use crate::query::Query;
+
+/// A tag, how many transactions and postings it is on and the values it was given
+#[derive(Serialize)]
+struct TagSummary {
+    tag: String,
+    count: usize,
+    values: BTreeSet<String>,
+}
+
+pub struct TagsReport {
+    tags: Vec<TagSummary>,
+}
+
+pub struct Tags {
+    query: Query,
+}
+
+impl Tags {
+    pub fn new(query: Query) -> Self {
+        Self { query }
+    }
+
+    /// Every tag on a matching transaction or posting, in alphabetical order
+    /// A tag is counted once for each transaction or posting it is on
+    fn summarise(&self, transactions: &[Transaction], postings: &[Posting]) -> Vec<TagSummary> {
+        let mut tags: BTreeMap<String, (usize, BTreeSet<String>)> = BTreeMap::new();
+        let mut add = |found: Vec<Tag>| {
+            let mut counted = BTreeSet::new();
+            for tag in found {
+                let (count, values) = tags.entry(tag.name.clone()).or_default();
+                if counted.insert(tag.name) {
+                    *count += 1;
+                }
+                values.extend(tag.value);
+            }
+        };
+
+        for transaction in transactions.iter().filter(|t| self.query.matches(t, postings)) {
+            add(transaction.tags());
+
+            for posting in transaction.postings.iter().map(|p| &postings[*p]) {
+                if self.query.matches_posting(posting) {
+                    add(posting.tags());
+                }
+            }
+        }
+
+        tags.into_iter()
+            .map(|(tag, (count, values))| TagSummary { tag, count, values })
+            .collect()
+    }
+}
+
+impl Command for Tags {
+    type Report<'j> = TagsReport;
+
+    fn run<'j>(&self, journal: &'j Journal) -> Self::Report<'j> {
+        TagsReport {
+            tags: self.summarise(journal.transactions(), journal.postings()),
+        }
+    }
+}
+
+impl Report for TagsReport {
+    fn print(&self, format: OutputFormat) {
+        let tags = &self.tags;
+
+        match format {
+            OutputFormat::Text => {
+                let width = tags.iter().map(|t| tree::width(&t.tag)).max().unwrap_or(0);
+                for tag in tags {
+                    let values: Vec<&str> = tag.values.iter().map(String::as_str).collect();
+                    println!(
+                        "{}{}  {:>6}  {}",
+                        tag.tag,
+                        " ".repeat(width - tree::width(&tag.tag)),
+                        tag.count,
+                        values.join(", ")
+                    );
+                }
+            }
+            OutputFormat::Json => print_json(&tags),
+            OutputFormat::Csv => print_csv(
+                &["tag", "count", "values"],
+                tags.iter().map(|t| {
+                    let values: Vec<&str> = t.values.iter().map(String::as_str).collect();
+                    vec![t.tag.clone(), t.count.to_string(), values.join(";")]
+                }),
+            ),
+            // The subcommand only accepts formats from OutputFormat::arg
+            OutputFormat::Html => unreachable!("tags has no HTML output"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::tests::{journal, query};
+
+    const JOURNAL: &str = "2020-01-01 Hotel  ; :holiday:work:
+    Expenses:Travel    £100  ; trip: Paris
+    Assets:Current  ; :holiday:
+
+2020-01-02 Hotel  ; trip: Rome
+    Expenses:Travel    £80  ; trip: Rome
+    Assets:Current
+";
+
+    fn tags(args: &[&str]) -> Vec<(String, usize, Vec<String>)> {
+        let journal = journal(JOURNAL);
+        Tags::new(query(args))
+            .run(&journal)
+            .tags
+            .into_iter()
+            .map(|tag| (tag.tag, tag.count, tag.values.into_iter().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn it_counts_each_transaction_and_posting_a_tag_is_on() {
+        assert_eq!(
+            tags(&[]),
+            vec![
+                ("holiday".to_owned(), 2, vec![]),
+                ("trip".to_owned(), 3, vec!["Paris".to_owned(), "Rome".to_owned()]),
+                ("work".to_owned(), 1, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_only_counts_tags_on_matching_postings() {
+        assert_eq!(
+            tags(&["Current"]),
+            vec![
+                ("holiday".to_owned(), 2, vec![]),
+                ("trip".to_owned(), 1, vec!["Rome".to_owned()]),
+                ("work".to_owned(), 1, vec![]),
+            ]
+        );
+    }
+}
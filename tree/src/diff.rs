@@ -0,0 +1,89 @@
+//! Comparing two trees built from the same kind of paths, such as the accounts in two snapshots of a journal.
+
+use crate::Tree;
+
+/// A node's values in the trees being compared
+/// Nodes only in the later tree have no value `before` and nodes only in the earlier one have no value `after`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diff<V> {
+    pub before: Option<V>,
+    pub after: Option<V>,
+}
+
+impl<V> Diff<V>
+where
+    V: PartialEq,
+{
+    pub fn is_added(&self) -> bool {
+        self.before.is_none() && self.after.is_some()
+    }
+
+    pub fn is_removed(&self) -> bool {
+        self.before.is_some() && self.after.is_none()
+    }
+
+    /// Whether the node was added, removed or has a different value
+    pub fn is_changed(&self) -> bool {
+        self.before != self.after
+    }
+}
+
+impl<V> Tree<V>
+where
+    V: Default + Clone,
+{
+    /// A tree with every path in this tree or `after`, holding the values from both
+    pub fn diff(&self, after: &Tree<V>) -> Tree<Diff<V>> {
+        let mut diff: Tree<Diff<V>> = Tree::new();
+
+        let root = diff.root();
+        let root = &mut diff.get_node_at_index_mut(root).unwrap().value;
+        root.before = Some(self.get_node_at_index(self.root()).unwrap().value.clone());
+        root.after = Some(after.get_node_at_index(after.root()).unwrap().value.clone());
+
+        for (_, _, path, node) in self.pre_order() {
+            let index = diff.add_path(&path);
+            diff.get_node_at_index_mut(index).unwrap().value.before = Some(node.value.clone());
+        }
+        for (_, _, path, node) in after.pre_order() {
+            let index = diff.add_path(&path);
+            diff.get_node_at_index_mut(index).unwrap().value.after = Some(node.value.clone());
+        }
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_diffs_trees() {
+        let mut before: Tree<usize> = Tree::new();
+        before.add_value_at_path(&["a", "b"], 1);
+        before.add_value_at_path(&["a", "c"], 2);
+        before.add_value_at_path(&["d"], 3);
+
+        let mut after: Tree<usize> = Tree::new();
+        after.add_value_at_path(&["a", "b"], 1);
+        after.add_value_at_path(&["a", "c"], 5);
+        after.add_value_at_path(&["e"], 4);
+
+        let diff = before.diff(&after);
+        let value = |path: &[&str]| diff.get_node_at_path(path).unwrap().value.clone();
+
+        assert!(!value(&["a", "b"]).is_changed());
+        assert_eq!(
+            value(&["a", "c"]),
+            Diff {
+                before: Some(2),
+                after: Some(5)
+            }
+        );
+        assert!(value(&["d"]).is_removed());
+        assert!(value(&["e"]).is_added());
+        assert!(!value(&["a", "c"]).is_added());
+    }
+}
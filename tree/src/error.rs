@@ -2,6 +2,7 @@
 pub enum Error {
     NodeOutOfBounds,
     NodeNotPresent,
+    RootNotRemovable,
 }
 
 impl std::fmt::Display for Error {
@@ -9,6 +10,7 @@ impl std::fmt::Display for Error {
         match self {
             Error::NodeOutOfBounds => write!(f, "node out of bounds"),
             Error::NodeNotPresent => write!(f, "node not present"),
+            Error::RootNotRemovable => write!(f, "the root node cannot be removed"),
         }
     }
 }
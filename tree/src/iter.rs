@@ -0,0 +1,221 @@
+//! Iterators over the nodes of a tree.
+//!
+//! Each iterator yields `(index, depth, full_path, node)` for every node below the root. Top-level nodes have a
+//! depth of zero and siblings are visited in alphabetical order, so the order doesn't depend on how the tree was
+//! built.
+
+use std::collections::VecDeque;
+
+use crate::{Node, Tree};
+
+/// A node's index in the arena, its depth, its full path and the node itself
+pub type Visit<'t, V> = (usize, usize, Vec<&'t str>, &'t Node<V>);
+
+/// Visits parents before their children, see `Tree::pre_order`
+pub struct PreOrder<'t, V>
+where
+    V: Default,
+{
+    tree: &'t Tree<V>,
+    /// The nodes still to visit, with the next on top
+    stack: Vec<(usize, Vec<&'t str>)>,
+}
+
+impl<'t, V> PreOrder<'t, V>
+where
+    V: Default,
+{
+    pub(crate) fn new(tree: &'t Tree<V>) -> Self {
+        let mut stack = child_paths(tree, tree.root(), &[]);
+        stack.reverse();
+        Self { tree, stack }
+    }
+}
+
+impl<'t, V> Iterator for PreOrder<'t, V>
+where
+    V: Default,
+{
+    type Item = Visit<'t, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, path) = self.stack.pop()?;
+        self.stack
+            .extend(child_paths(self.tree, index, &path).into_iter().rev());
+        Some(visit(self.tree, index, path))
+    }
+}
+
+/// Visits children before their parents, see `Tree::post_order`
+pub struct PostOrder<'t, V>
+where
+    V: Default,
+{
+    tree: &'t Tree<V>,
+    /// The nodes still to visit, with the next on top, and whether their children have been put above them
+    stack: Vec<(usize, Vec<&'t str>, bool)>,
+}
+
+impl<'t, V> PostOrder<'t, V>
+where
+    V: Default,
+{
+    pub(crate) fn new(tree: &'t Tree<V>) -> Self {
+        let stack = child_paths(tree, tree.root(), &[])
+            .into_iter()
+            .rev()
+            .map(|(index, path)| (index, path, false))
+            .collect();
+        Self { tree, stack }
+    }
+}
+
+impl<'t, V> Iterator for PostOrder<'t, V>
+where
+    V: Default,
+{
+    type Item = Visit<'t, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, path, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(visit(self.tree, index, path));
+            }
+
+            let children = child_paths(self.tree, index, &path);
+            self.stack.push((index, path, true));
+            self.stack
+                .extend(children.into_iter().rev().map(|(index, path)| (index, path, false)));
+        }
+    }
+}
+
+/// Visits every node at one depth before any at the next, see `Tree::breadth_first`
+pub struct BreadthFirst<'t, V>
+where
+    V: Default,
+{
+    tree: &'t Tree<V>,
+    queue: VecDeque<(usize, Vec<&'t str>)>,
+}
+
+impl<'t, V> BreadthFirst<'t, V>
+where
+    V: Default,
+{
+    pub(crate) fn new(tree: &'t Tree<V>) -> Self {
+        let queue = child_paths(tree, tree.root(), &[]).into();
+        Self { tree, queue }
+    }
+}
+
+impl<'t, V> Iterator for BreadthFirst<'t, V>
+where
+    V: Default,
+{
+    type Item = Visit<'t, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, path) = self.queue.pop_front()?;
+        self.queue.extend(child_paths(self.tree, index, &path));
+        Some(visit(self.tree, index, path))
+    }
+}
+
+/// The indexes and full paths of the children of the node at `index`, whose full path is `path`
+fn child_paths<'t, V>(tree: &'t Tree<V>, index: usize, path: &[&'t str]) -> Vec<(usize, Vec<&'t str>)>
+where
+    V: Default,
+{
+    tree.children(index)
+        .into_iter()
+        .map(|(name, child)| {
+            let mut child_path = path.to_vec();
+            child_path.push(name);
+            (child, child_path)
+        })
+        .collect()
+}
+
+fn visit<'t, V>(tree: &'t Tree<V>, index: usize, path: Vec<&'t str>) -> Visit<'t, V>
+where
+    V: Default,
+{
+    let node = tree.get_node_at_index(index).unwrap();
+    (index, path.len() - 1, path, node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a(1) has children c(2) and b(3), and b has a child d(4)
+    fn tree() -> Tree<usize> {
+        let mut tree = Tree::new();
+        tree.add_value_at_path(&["a"], 1);
+        tree.add_value_at_path(&["a", "c"], 2);
+        tree.add_value_at_path(&["a", "b"], 3);
+        tree.add_value_at_path(&["a", "b", "d"], 4);
+        tree
+    }
+
+    fn paths<'t, I>(visits: I) -> Vec<(usize, String, usize)>
+    where
+        I: Iterator<Item = Visit<'t, usize>>,
+    {
+        visits
+            .map(|(_, depth, path, node)| (depth, path.join(":"), node.value))
+            .collect()
+    }
+
+    fn expected(visits: &[(usize, &str, usize)]) -> Vec<(usize, String, usize)> {
+        visits
+            .iter()
+            .map(|(depth, path, value)| (*depth, path.to_string(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn it_iterates_in_pre_order() {
+        let tree = tree();
+        assert_eq!(
+            paths(tree.pre_order()),
+            expected(&[(0, "a", 1), (1, "a:b", 3), (2, "a:b:d", 4), (1, "a:c", 2)])
+        );
+    }
+
+    #[test]
+    fn it_iterates_in_post_order() {
+        let tree = tree();
+        assert_eq!(
+            paths(tree.post_order()),
+            expected(&[(2, "a:b:d", 4), (1, "a:b", 3), (1, "a:c", 2), (0, "a", 1)])
+        );
+    }
+
+    #[test]
+    fn it_iterates_breadth_first() {
+        let tree = tree();
+        assert_eq!(
+            paths(tree.breadth_first()),
+            expected(&[(0, "a", 1), (1, "a:b", 3), (1, "a:c", 2), (2, "a:b:d", 4)])
+        );
+    }
+
+    #[test]
+    fn it_yields_the_index_of_each_node() {
+        let tree = tree();
+        for (index, _, _, node) in tree.pre_order() {
+            assert_eq!(tree.get_node_at_index(index).unwrap().value, node.value);
+        }
+    }
+
+    #[test]
+    fn it_iterates_over_nothing_in_an_empty_tree() {
+        let tree: Tree<usize> = Tree::new();
+        assert_eq!(tree.pre_order().count(), 0);
+        assert_eq!(tree.post_order().count(), 0);
+        assert_eq!(tree.breadth_first().count(), 0);
+    }
+}
@@ -1,8 +1,16 @@
+mod diff;
 mod error;
+mod iter;
 mod node;
+mod render;
+#[cfg(feature = "serde")]
+mod serialize;
 mod tree;
 
 // Adding the 'crate::' here silences a warning about ambiguous names
 pub use crate::tree::Tree;
+pub use diff::Diff;
 pub use error::Error;
+pub use iter::{BreadthFirst, PostOrder, PreOrder, Visit};
 pub use node::Node;
+pub use render::{width, Guides, Renderer};
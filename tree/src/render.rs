@@ -0,0 +1,337 @@
+//! Rendering trees as text.
+//!
+//! A rendered tree has one line per node below the root, in pre-order. Each line starts with the node's value
+//! columns, right-aligned to the widest cell in the column, followed by the node's name:
+//!
+//! ```text
+//!   £1000.00  Assets
+//!    £950.00  ├─ Current
+//!     £50.00  └─ Savings
+//! ```
+//!
+//! Siblings are in alphabetical order unless the renderer is given another, and a footer such as a total can be
+//! written below a rule under the columns.
+//!
+//! A cell can have several lines, e.g. an amount in each commodity. The node then takes up as many lines as its
+//! tallest cell, with its name on the last one as ledger does.
+//!
+//! Widths are measured in terminal columns, so names and values with wide or combining characters line up.
+
+use std::cmp::Ordering;
+use std::io;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::{Node, Tree};
+
+/// How a node's depth is shown before its name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Guides {
+    /// Two spaces for each ancestor below the root
+    Indent,
+    /// Box-drawing lines joining each node to its parent
+    Lines,
+}
+
+type Cell<'r, V> = Box<dyn Fn(&Node<V>) -> String + 'r>;
+type Compare<'r, V> = Box<dyn Fn(&Node<V>, &Node<V>) -> Ordering + 'r>;
+
+struct Column<'r, V>
+where
+    V: Default,
+{
+    heading: String,
+    cell: Cell<'r, V>,
+}
+
+/// Renders trees with any number of value columns
+pub struct Renderer<'r, V>
+where
+    V: Default,
+{
+    columns: Vec<Column<'r, V>>,
+    guides: Guides,
+    /// The order of siblings, if not by name
+    order: Option<Compare<'r, V>>,
+    /// Cells written below the nodes, one for each column
+    footer: Option<Vec<String>>,
+}
+
+/// A node's lines of output before they are padded
+struct Line {
+    cells: Vec<String>,
+    /// The guides and name
+    label: String,
+    /// The guides on the lines before the last when a cell has several lines
+    continuation: String,
+}
+
+impl<'r, V> Renderer<'r, V>
+where
+    V: Default,
+{
+    pub fn new(guides: Guides) -> Self {
+        Self {
+            columns: Vec::new(),
+            guides,
+            order: None,
+            footer: None,
+        }
+    }
+
+    /// Orders siblings with `compare` instead of by name
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: Fn(&Node<V>, &Node<V>) -> Ordering + 'r,
+    {
+        self.order = Some(Box::new(compare));
+        self
+    }
+
+    /// Writes a rule under the columns after the nodes, followed by `cells`, which has one cell for each column
+    pub fn footer(mut self, cells: Vec<String>) -> Self {
+        self.footer = Some(cells);
+        self
+    }
+
+    /// Adds a column whose cells are `cell` applied to each node
+    /// Columns are written in the order they are added and headings are only written if a column has one
+    pub fn column<F>(mut self, heading: &str, cell: F) -> Self
+    where
+        F: Fn(&Node<V>) -> String + 'r,
+    {
+        self.columns.push(Column {
+            heading: heading.to_owned(),
+            cell: Box::new(cell),
+        });
+        self
+    }
+
+    /// Writes the descendants of the node at `root`, and the footer if there is one
+    /// Columns whose heading and cells are all empty are left out
+    pub fn render<W: io::Write>(&self, tree: &Tree<V>, root: usize, writer: &mut W) -> io::Result<()> {
+        let mut lines = Vec::new();
+        self.add_lines(tree, root, true, "", &mut lines);
+
+        let footer = self.footer.as_ref().map(|cells| Line {
+            cells: cells.clone(),
+            label: String::new(),
+            continuation: String::new(),
+        });
+
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let cells = lines
+                    .iter()
+                    .chain(&footer)
+                    .flat_map(|line| line.cells[i].lines().map(width));
+                cells.chain(Some(width(&column.heading))).max().unwrap_or(0)
+            })
+            .collect();
+
+        if !lines.is_empty() && self.columns.iter().any(|column| !column.heading.is_empty()) {
+            let headings = self.columns.iter().map(|column| column.heading.clone());
+            lines.insert(
+                0,
+                Line {
+                    cells: headings.collect(),
+                    label: String::new(),
+                    continuation: String::new(),
+                },
+            );
+        }
+
+        if let Some(footer) = footer {
+            lines.push(Line {
+                cells: widths.iter().map(|width| "-".repeat(*width)).collect(),
+                label: String::new(),
+                continuation: String::new(),
+            });
+            lines.push(footer);
+        }
+
+        for line in lines {
+            let cells: Vec<Vec<&str>> = line.cells.iter().map(|cell| cell.lines().collect()).collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+
+            for row in 0..height {
+                let mut text = String::new();
+                for (cell, width) in cells.iter().zip(&widths).filter(|(_, width)| **width > 0) {
+                    // Cells with fewer lines are aligned to the bottom, next to the name
+                    let cell = (row + cell.len()).checked_sub(height).map_or("", |i| cell[i]);
+                    text.push_str(&" ".repeat(width - self::width(cell)));
+                    text.push_str(cell);
+                    text.push_str("  ");
+                }
+                text.push_str(if row + 1 == height {
+                    &line.label
+                } else {
+                    &line.continuation
+                });
+                // The heading line has no label
+                writeln!(writer, "{}", text.trim_end())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds lines for the children of the node at `index`, which are top-level if it is the node being rendered
+    /// `prefix` is what's drawn before the guides of the children
+    fn add_lines(&self, tree: &Tree<V>, index: usize, top_level: bool, prefix: &str, lines: &mut Vec<Line>) {
+        let mut children = tree.children(index);
+        if let Some(compare) = &self.order {
+            let node = |index| tree.get_node_at_index(index).unwrap();
+            children.sort_by(|(_, a), (_, b)| compare(node(*a), node(*b)));
+        }
+        let count = children.len();
+
+        for (i, (name, child)) in children.into_iter().enumerate() {
+            let node = tree.get_node_at_index(child).unwrap();
+            let last = i + 1 == count;
+
+            let (guide, child_prefix) = match (self.guides, top_level) {
+                (Guides::Indent, _) => ("", format!("{}  ", prefix)),
+                // Top-level nodes aren't joined to anything
+                (Guides::Lines, true) => ("", String::new()),
+                (Guides::Lines, false) if last => ("└─ ", format!("{}   ", prefix)),
+                (Guides::Lines, false) => ("├─ ", format!("{}│  ", prefix)),
+            };
+            // The line joining the node to its parent runs down to its name
+            let continuation = match guide {
+                "" => prefix.to_owned(),
+                _ => format!("{}│", prefix),
+            };
+
+            lines.push(Line {
+                cells: self.columns.iter().map(|column| (column.cell)(node)).collect(),
+                label: format!("{}{}{}", prefix, guide, name),
+                continuation,
+            });
+            self.add_lines(tree, child, false, &child_prefix, lines);
+        }
+    }
+}
+
+/// The number of terminal columns `text` takes up
+pub fn width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> Tree<usize> {
+        let mut tree = Tree::new();
+        tree.add_value_at_path(&["a"], 1000);
+        tree.add_value_at_path(&["a", "b"], 10);
+        tree.add_value_at_path(&["a", "b", "c"], 1);
+        tree.add_value_at_path(&["a", "d"], 5);
+        tree.add_value_at_path(&["Jéby\u{306}"], 7);
+        tree
+    }
+
+    fn render(renderer: Renderer<usize>, tree: &Tree<usize>) -> String {
+        let mut output = Vec::new();
+        renderer.render(tree, tree.root(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn it_renders_indented_names() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Indent);
+        assert_eq!(render(renderer, &tree), "Jéby\u{306}\na\n  b\n    c\n  d\n");
+    }
+
+    #[test]
+    fn it_renders_guide_lines() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Lines);
+        assert_eq!(render(renderer, &tree), "Jéby\u{306}\na\n├─ b\n│  └─ c\n└─ d\n");
+    }
+
+    #[test]
+    fn it_aligns_columns_by_display_width() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Indent)
+            .column("", |node: &Node<usize>| node.value.to_string())
+            .column("", |node: &Node<usize>| match node.value {
+                7 => "Jéby\u{306}".to_owned(),
+                _ => "ab".to_owned(),
+            });
+        assert_eq!(
+            render(renderer, &tree),
+            "   7  Jéby\u{306}  Jéby\u{306}\n1000    ab  a\n  10    ab    b\n   1    ab      c\n   5    ab    d\n"
+        );
+    }
+
+    #[test]
+    fn it_leaves_out_empty_columns() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Indent).column("", |_| String::new());
+        assert_eq!(render(renderer, &tree), "Jéby\u{306}\na\n  b\n    c\n  d\n");
+    }
+
+    #[test]
+    fn it_writes_headings() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a"], 1);
+        let renderer = Renderer::new(Guides::Indent)
+            .column("value", |node: &Node<usize>| node.value.to_string())
+            .column("", |_| String::new());
+        assert_eq!(render(renderer, &tree), "value\n    1  a\n");
+    }
+
+    #[test]
+    fn it_writes_cells_with_several_lines() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Lines)
+            .column("", |node: &Node<usize>| match node.value {
+                10 => "£10\n$2".to_owned(),
+                value => value.to_string(),
+            })
+            .column("", |node: &Node<usize>| match node.value {
+                1 => "x\ny\nz".to_owned(),
+                _ => String::new(),
+            });
+        assert_eq!(
+            render(renderer, &tree),
+            "   7     Jéby\u{306}\n1000     a\n £10     │\n  $2     ├─ b\n      x  │  │\n      y  │  │\n   1  z  │  └─ c\n   5     └─ d\n"
+        );
+    }
+
+    #[test]
+    fn it_sorts_siblings() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Indent).sort_by(|a: &Node<usize>, b: &Node<usize>| a.value.cmp(&b.value));
+        assert_eq!(render(renderer, &tree), "Jéby\u{306}\na\n  d\n  b\n    c\n");
+    }
+
+    #[test]
+    fn it_writes_footers() {
+        let tree = tree();
+        let renderer = Renderer::new(Guides::Indent)
+            .column("", |node: &Node<usize>| node.value.to_string())
+            .footer(vec!["10000".to_owned()]);
+        assert_eq!(
+            render(renderer, &tree),
+            "    7  Jéby\u{306}\n 1000  a\n   10    b\n    1      c\n    5    d\n-----\n10000\n"
+        );
+
+        // The footer is written even if there are no nodes
+        let renderer = Renderer::new(Guides::Indent)
+            .column("", |node: &Node<usize>| node.value.to_string())
+            .footer(vec!["0".to_owned()]);
+        assert_eq!(render(renderer, &Tree::new()), "-\n0\n");
+    }
+
+    #[test]
+    fn it_measures_combining_characters() {
+        assert_eq!(width("Jéby\u{306}"), 4);
+    }
+}
@@ -0,0 +1,167 @@
+//! Serialisation of trees, enabled with the `serde` feature.
+//!
+//! A tree is written as a sequence of its top-level nodes and each node as:
+//!
+//! ```text
+//! {
+//!   "name": string,      // the last component of the node's path
+//!   "path": string,      // the full path, joined with ':'
+//!   "value": V,
+//!   "children": [node]   // in alphabetical order
+//! }
+//! ```
+//!
+//! Trees are read back from the same form. Nodes are placed by their names and nesting, and `path` is ignored.
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+
+use crate::Tree;
+
+impl<V> Serialize for Tree<V>
+where
+    V: Default + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Children {
+            tree: self,
+            index: self.root(),
+            path: "",
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The children of the node at `index`, whose full path is `path`
+struct Children<'t, V>
+where
+    V: Default,
+{
+    tree: &'t Tree<V>,
+    index: usize,
+    path: &'t str,
+}
+
+impl<'t, V> Serialize for Children<'t, V>
+where
+    V: Default + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let children = self.tree.children(self.index);
+        let mut seq = serializer.serialize_seq(Some(children.len()))?;
+
+        for (name, index) in children {
+            let path = if self.path.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{}:{}", self.path, name)
+            };
+
+            seq.serialize_element(&NodeWithPath {
+                tree: self.tree,
+                index,
+                name,
+                path: &path,
+            })?;
+        }
+
+        seq.end()
+    }
+}
+
+struct NodeWithPath<'t, V>
+where
+    V: Default,
+{
+    tree: &'t Tree<V>,
+    index: usize,
+    name: &'t str,
+    path: &'t str,
+}
+
+impl<'t, V> Serialize for NodeWithPath<'t, V>
+where
+    V: Default + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.tree.get_node_at_index(self.index).unwrap();
+
+        let mut state = serializer.serialize_struct("Node", 4)?;
+        state.serialize_field("name", self.name)?;
+        state.serialize_field("path", self.path)?;
+        state.serialize_field("value", &node.value)?;
+        state.serialize_field(
+            "children",
+            &Children {
+                tree: self.tree,
+                index: self.index,
+                path: self.path,
+            },
+        )?;
+        state.end()
+    }
+}
+
+impl<'de, V> Deserialize<'de> for Tree<V>
+where
+    V: Default + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let nodes: Vec<SerialisedNode<V>> = Vec::deserialize(deserializer)?;
+
+        let mut tree = Tree::new();
+        // The nodes still to add and their parents' paths
+        let mut stack: Vec<(SerialisedNode<V>, Vec<String>)> = nodes.into_iter().map(|n| (n, vec![])).collect();
+        while let Some((node, mut path)) = stack.pop() {
+            path.push(node.name);
+            let names: Vec<&str> = path.iter().map(String::as_str).collect();
+            tree.add_value_at_path(&names, node.value);
+
+            stack.extend(node.children.into_iter().map(|child| (child, path.clone())));
+        }
+
+        Ok(tree)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SerialisedNode<V> {
+    name: String,
+    value: V,
+    #[serde(default = "Vec::new")]
+    children: Vec<SerialisedNode<V>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_serialises_nested_nodes() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b"], 2);
+
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"name":"a","path":"a","value":0,"children":[{"name":"b","path":"a:b","value":2,"children":[]}]}]"#
+        );
+    }
+
+    #[test]
+    fn it_deserialises_what_it_serialises() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b"], 2);
+        tree.add_value_at_path(&["a", "c"], 3);
+        tree.add_value_at_path(&["d"], 4);
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let read: Tree<usize> = serde_json::from_str(&json).unwrap();
+
+        let mut nodes = vec![];
+        read.walk(|path, node| nodes.push((path.join(":"), node.value)));
+        let expected = vec![("a", 0), ("a:b", 2), ("a:c", 3), ("d", 4)];
+        let expected: Vec<(String, usize)> = expected.into_iter().map(|(p, v)| (p.to_owned(), v)).collect();
+        assert_eq!(nodes, expected);
+    }
+}
@@ -1,16 +1,27 @@
 use crate::Error;
 use crate::Node;
+use crate::{BreadthFirst, Guides, PostOrder, PreOrder, Renderer};
 use std::collections::HashMap;
 
-pub struct Tree<'a, V>
+#[derive(Clone)]
+pub struct Tree<V>
 where
     V: Default,
 {
-    arena: Vec<Option<Node<'a, V>>>,
+    arena: Vec<Option<Node<V>>>,
     root: usize,
 }
 
-impl<'a, V> Tree<'a, V>
+impl<V> Default for Tree<V>
+where
+    V: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Tree<V>
 where
     V: Default,
 {
@@ -30,11 +41,11 @@ where
     /// Returns the index of the last path component
     /// Will add all intermidiate nodes as necessary
     /// These nodes will have the default value
-    pub fn add_path(&mut self, path: &mut [&'a str]) -> usize {
-        match path.split_last_mut() {
+    pub fn add_path(&mut self, path: &[&str]) -> usize {
+        match path.split_last() {
             None => {
                 // Eventually, we have to return the root
-                return self.root;
+                self.root
             }
             Some((component, path)) => {
                 // println!("popped {}", component);
@@ -50,14 +61,14 @@ where
                 let parent = self.arena[parent_index].as_mut().unwrap();
 
                 // TODO check if the parent has the component before blindly adding
-                if let Some(child_index) = parent.children.get(component) {
+                if let Some(child_index) = parent.children.get(*component) {
                     // println!("\tfound child for {} on {}", component, parent_index);
                     return *child_index;
                 }
 
                 // println!("\tdid not find child for {} on {}", component, parent_index);
 
-                parent.children.insert(component, node_index);
+                parent.children.insert(component.to_string(), node_index);
 
                 // Add the new node
                 let node = Node::new(Some(parent_index), V::default());
@@ -70,20 +81,20 @@ where
     }
 
     /// Convenience method for adding a node and setting it's value
-    pub fn add_value_at_path(&mut self, path: &mut [&'a str], value: V) {
+    pub fn add_value_at_path(&mut self, path: &[&str], value: V) {
         let index = self.add_path(path);
         let node = self.get_node_at_index_mut(index).unwrap();
         node.value = value;
     }
 
-    pub fn get_node_at_index(&self, index: usize) -> Option<&Node<'a, V>> {
+    pub fn get_node_at_index(&self, index: usize) -> Option<&Node<V>> {
         match self.arena.get(index) {
             None => None,
             Some(node) => node.as_ref(),
         }
     }
 
-    pub fn get_node_at_index_mut(&mut self, index: usize) -> Option<&mut Node<'a, V>> {
+    pub fn get_node_at_index_mut(&mut self, index: usize) -> Option<&mut Node<V>> {
         match self.arena.get_mut(index) {
             None => None,
             Some(node) => node.as_mut(),
@@ -91,7 +102,7 @@ where
     }
 
     /// If the path did not exist, return None
-    pub fn get_node_at_path(&'a self, path: &mut [&'a str]) -> Option<&Node<'a, V>> {
+    pub fn get_node_at_path(&self, path: &[&str]) -> Option<&Node<V>> {
         match index_of_node_at_path(&self.arena, path, self.root) {
             None => None,
             Some(index) => self.get_node_at_index(index),
@@ -99,7 +110,7 @@ where
     }
 
     /// If the path did not exist, return None
-    pub fn get_node_at_path_mut(&mut self, path: &mut [&'a str]) -> Option<&mut Node<'a, V>> {
+    pub fn get_node_at_path_mut(&mut self, path: &[&str]) -> Option<&mut Node<V>> {
         match index_of_node_at_path(&self.arena, path, self.root) {
             None => None,
             Some(index) => self.get_node_at_index_mut(index),
@@ -109,7 +120,7 @@ where
     /// Applies `F` to root and all ancestors
     pub fn walk_ancestors<F>(&mut self, root: usize, mut f: F) -> Result<(), Error>
     where
-        F: FnMut(&mut Node<'a, V>),
+        F: FnMut(&mut Node<V>),
     {
         match self.arena.get_mut(root) {
             None => Err(Error::NodeOutOfBounds),
@@ -131,89 +142,286 @@ where
     /// Applies `F` to all descendants of root
     pub fn walk_descendants<F>(&mut self, root: usize, mut f: F)
     where
-        F: FnMut(&mut Node<'a, V>) + Copy,
+        F: FnMut(&mut Node<V>),
     {
-        if let Some(indices) = self.child_indices(root) {
-            for index in indices {
-                let node = self.arena[index].as_mut().unwrap();
-                f(node);
-            }
+        let mut stack = self.child_indices(root);
+        while let Some(index) = stack.pop() {
+            stack.extend(self.child_indices(index));
+            f(self.arena[index].as_mut().unwrap());
         }
     }
 
-    fn child_indices(&self, root: usize) -> Option<Vec<usize>> {
-        match self.arena.get(root) {
-            None => return None,
-            Some(node) => match node {
-                None => return None,
-                Some(node) => {
-                    if node.children.len() == 0 {
-                        return None;
-                    }
+    fn child_indices(&self, index: usize) -> Vec<usize> {
+        match self.arena.get(index) {
+            Some(Some(node)) => node.children.values().copied().collect(),
+            _ => vec![],
+        }
+    }
 
-                    Some(node.children.values().fold(vec![], |mut acc, index| {
-                        // Add the index of the child
-                        acc.push(*index);
+    /// Calls `f` with the full path and node of every node below the root, parents before their children
+    /// and siblings in alphabetical order
+    pub fn walk<F>(&self, mut f: F)
+    where
+        F: FnMut(&[&str], &Node<V>),
+    {
+        for (_, _, path, node) in self.pre_order() {
+            f(&path, node);
+        }
+    }
 
-                        // Add the indicies of the grandchildren
-                        if let Some(ref mut v) = self.child_indices(*index) {
-                            acc.append(v);
-                        }
+    /// Iterates over every node below the root, parents before their children and siblings in alphabetical order
+    pub fn pre_order(&self) -> PreOrder<'_, V> {
+        PreOrder::new(self)
+    }
 
-                        acc
-                    }))
-                }
-            },
+    /// Iterates over every node below the root, children before their parents and siblings in alphabetical order
+    pub fn post_order(&self) -> PostOrder<'_, V> {
+        PostOrder::new(self)
+    }
+
+    /// Iterates over every node below the root, shallower nodes first and siblings in alphabetical order
+    pub fn breadth_first(&self) -> BreadthFirst<'_, V> {
+        BreadthFirst::new(self)
+    }
+
+    /// The names and indexes of a node's children in alphabetical order
+    pub fn children(&self, index: usize) -> Vec<(&str, usize)> {
+        let mut children: Vec<(&str, usize)> = match self.arena.get(index) {
+            Some(Some(node)) => node
+                .children
+                .iter()
+                .map(|(name, index)| (name.as_str(), *index))
+                .collect(),
+            _ => vec![],
+        };
+        children.sort_by(|a, b| a.0.cmp(b.0));
+        children
+    }
+
+    /// The index of the root node, which has no name and is the parent of the top-level nodes
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Combines every node's value into its parent's, children before their parents, so each node ends up with the
+    /// total of its own value and all of its descendants'
+    /// `combine` should be associative and commutative, as siblings are combined in no particular order
+    pub fn aggregate<F>(&mut self, mut combine: F)
+    where
+        F: FnMut(&mut V, &V),
+    {
+        // Reversing an order where parents come before their children puts every child before its parent
+        let mut order = Vec::with_capacity(self.arena.len());
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.child_indices(index));
+        }
+
+        for index in order.into_iter().rev() {
+            let node = self.arena[index].as_mut().unwrap();
+            let parent = match node.parent {
+                None => continue,
+                Some(parent) => parent,
+            };
+            let value = std::mem::take(&mut node.value);
+
+            combine(&mut self.arena[parent].as_mut().unwrap().value, &value);
+            self.arena[index].as_mut().unwrap().value = value;
         }
     }
 
-    pub fn display<F>(&self, root: &Option<usize>, f: F)
+    /// Like `aggregate`, combining the subtrees of each node's children in parallel
+    #[cfg(feature = "rayon")]
+    pub fn par_aggregate<F>(&mut self, combine: F)
     where
-        F: Fn(&Node<'a, V>) -> Option<String> + Copy,
+        V: Clone + Send + Sync,
+        F: Fn(&mut V, &V) + Sync,
     {
-        // Level of indent to start at
-        let indent = 0;
+        let (_, totals) = par_totals(&self.arena, self.root, &combine);
+        for (index, total) in totals {
+            self.arena[index].as_mut().unwrap().value = total;
+        }
+    }
 
-        let root_node: &Node<'a, V> = match root {
-            None => self.arena[self.root].as_ref().unwrap(),
-            Some(root) => self.arena[*root].as_ref().unwrap(),
+    /// Removes the node at `index` and all of its descendants
+    /// The indexes of other nodes are unchanged until the tree is compacted
+    pub fn remove_subtree(&mut self, index: usize) -> Result<(), Error> {
+        if index == self.root {
+            return Err(Error::RootNotRemovable);
+        }
+
+        let parent = match self.arena.get(index) {
+            None => return Err(Error::NodeOutOfBounds),
+            Some(None) => return Err(Error::NodeNotPresent),
+            Some(Some(node)) => node.parent,
         };
 
-        let mut children: Vec<(_, _)> = root_node.children.iter().collect();
-        children.sort_by(|a, b| a.0.cmp(b.0));
-        for (name, child_index) in children {
-            let child = self.arena[*child_index].as_ref().unwrap();
+        if let Some(Some(parent)) = parent.and_then(|p| self.arena.get_mut(p)) {
+            parent.children.retain(|_, child| *child != index);
+        }
+
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            stack.extend(self.child_indices(index));
+            self.arena[index] = None;
+        }
+
+        Ok(())
+    }
 
-            // indenting from https://stackoverflow.com/a/42273813
-            match f(child) {
-                None => println!("{:indent$}{}", "", name, indent = indent),
-                Some(t) => println!("{:>20}  {:indent$}{}", t, "", name, indent = indent * 2),
+    /// Removes every node for which `keep` is false for the node and all of its descendants, so the nodes left are
+    /// those that pass and their ancestors
+    /// `keep` is called with each node's full path, children before their parents
+    pub fn prune<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&[&str], &Node<V>) -> bool,
+    {
+        let mut kept = vec![false; self.arena.len()];
+        for (index, _, path, node) in self.post_order() {
+            if keep(&path, node) {
+                kept[index] = true;
+            }
+            if kept[index] {
+                if let Some(parent) = node.parent {
+                    kept[parent] = true;
+                }
             }
+        }
 
-            child.display(indent, &self.arena, f);
+        // Removing the highest nodes that failed removes the rest with them
+        let removed: Vec<usize> = self
+            .pre_order()
+            .filter(|(index, _, _, node)| !kept[*index] && node.parent.is_some_and(|p| p == self.root || kept[p]))
+            .map(|(index, _, _, _)| index)
+            .collect();
+        for index in removed {
+            self.remove_subtree(index).unwrap();
         }
     }
+
+    /// Moves the nodes left after removals to the start of the arena
+    /// Returns the new index of each old index, or None for nodes that had been removed
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let mut new_indices = Vec::with_capacity(self.arena.len());
+        let mut count = 0;
+        for node in &self.arena {
+            match node {
+                None => new_indices.push(None),
+                Some(_) => {
+                    new_indices.push(Some(count));
+                    count += 1;
+                }
+            }
+        }
+
+        let arena = std::mem::take(&mut self.arena);
+        self.arena = arena
+            .into_iter()
+            .flatten()
+            .map(|mut node| {
+                node.parent = node.parent.and_then(|p| new_indices[p]);
+                for child in node.children.values_mut() {
+                    *child = new_indices[*child].unwrap();
+                }
+                Some(node)
+            })
+            .collect();
+        self.root = new_indices[self.root].unwrap();
+
+        new_indices
+    }
+
+    /// Prints the descendants of `root`, or of the root node if it is None, after the value `f` gives each node
+    pub fn display<F>(&self, root: &Option<usize>, f: F)
+    where
+        F: Fn(&Node<V>) -> Option<String>,
+    {
+        let renderer = Renderer::new(Guides::Indent).column("", move |node| f(node).unwrap_or_default());
+        let stdout = std::io::stdout();
+        renderer
+            .render(self, root.unwrap_or(self.root), &mut stdout.lock())
+            .expect("failed to write to stdout");
+    }
+}
+
+impl<V> Tree<V>
+where
+    V: Default + Clone,
+{
+    /// A copy of the node at `path` and its descendants, with that node as the root
+    /// If the path did not exist, return None
+    pub fn subtree(&self, path: &[&str]) -> Option<Tree<V>> {
+        let index = index_of_node_at_path(&self.arena, path, self.root)?;
+
+        let mut tree = Tree::new();
+        tree.arena[tree.root].as_mut().unwrap().value = self.arena[index].as_ref()?.value.clone();
+
+        // The index of a node in this tree, its name and the index of its copy's parent in the new one
+        let mut stack: Vec<(usize, &str, usize)> = self
+            .children(index)
+            .into_iter()
+            .map(|(name, child)| (child, name, tree.root))
+            .collect();
+        while let Some((index, name, parent)) = stack.pop() {
+            let node = self.arena[index].as_ref().unwrap();
+            let copy = tree.arena.len();
+            tree.arena.push(Some(Node::new(Some(parent), node.value.clone())));
+            tree.arena[parent]
+                .as_mut()
+                .unwrap()
+                .children
+                .insert(name.to_owned(), copy);
+
+            stack.extend(node.children.iter().map(|(name, child)| (*child, name.as_str(), copy)));
+        }
+
+        Some(tree)
+    }
+}
+
+/// The total of the node at `index` and the totals of it and all of its descendants, by index
+#[cfg(feature = "rayon")]
+fn par_totals<V, F>(arena: &[Option<Node<V>>], index: usize, combine: &F) -> (V, Vec<(usize, V)>)
+where
+    V: Default + Clone + Send + Sync,
+    F: Fn(&mut V, &V) + Sync,
+{
+    use rayon::prelude::*;
+
+    let node = arena[index].as_ref().unwrap();
+    let children: Vec<(V, Vec<(usize, V)>)> = node
+        .children
+        .par_iter()
+        .map(|(_, child)| par_totals(arena, *child, combine))
+        .collect();
+
+    let mut total = node.value.clone();
+    let mut totals = Vec::new();
+    for (child_total, mut child_totals) in children {
+        combine(&mut total, &child_total);
+        totals.append(&mut child_totals);
+    }
+    totals.push((index, total.clone()));
+
+    (total, totals)
 }
 
 // We need this to be a free function so we don't have multiple borrows of the tree
-fn index_of_node_at_path<'a, V>(arena: &Vec<Option<Node<'a, V>>>, path: &mut [&'a str], root: usize) -> Option<usize>
+fn index_of_node_at_path<V>(arena: &Vec<Option<Node<V>>>, path: &[&str], root: usize) -> Option<usize>
 where
     V: Default,
 {
-    match path.split_last_mut() {
+    match path.split_last() {
         // If we can't split the path anymore, we've got to the root
-        None => return Some(root),
+        None => Some(root),
 
         Some((component, rest)) => match index_of_node_at_path(arena, rest, root) {
-            None => return None,
+            None => None,
 
             Some(index) => match arena.get(index) {
-                None => return None,
-
-                Some(node) => match node {
-                    None => return None,
-                    Some(node) => return node.children.get(component).map(|i| *i),
-                },
+                Some(Some(node)) => node.children.get(*component).copied(),
+                _ => None,
             },
         },
     }
@@ -225,55 +433,65 @@ mod tests {
 
     #[test]
     fn it_adds_paths() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        let c_index = tree.add_path(&mut path);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        let c_index = tree.add_path(&path);
         assert_eq!(3, c_index);
     }
 
     #[test]
     fn it_adds_values() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        tree.add_value_at_path(&mut path, 42);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        tree.add_value_at_path(&path, 42);
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b", "c"]) {
+        match tree.get_node_at_path(&["a", "b", "c"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 42),
         }
     }
 
+    #[test]
+    fn it_outlives_the_names_it_was_built_from() {
+        let mut tree: Tree<usize> = Tree::new();
+        for account in &["a:b", "a:c"] {
+            let account = account.to_string();
+            let path: Vec<&str> = account.split(':').collect();
+            tree.add_value_at_path(&path, 1);
+        }
+
+        assert_eq!(tree.get_node_at_path(&["a", "c"]).map(|n| n.value), Some(1));
+        assert_eq!(tree.children(tree.root()), vec![("a", 1)]);
+    }
+
     #[test]
     fn it_finds_the_index_of_a_path() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        tree.add_path(&mut path);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        tree.add_path(&path);
 
-        assert_eq!(index_of_node_at_path(&tree.arena, &mut ["a"], tree.root), Some(1));
-        assert_eq!(index_of_node_at_path(&tree.arena, &mut ["a", "b"], tree.root), Some(2));
-        assert_eq!(
-            index_of_node_at_path(&tree.arena, &mut ["a", "b", "c"], tree.root),
-            Some(3)
-        );
+        assert_eq!(index_of_node_at_path(&tree.arena, &["a"], tree.root), Some(1));
+        assert_eq!(index_of_node_at_path(&tree.arena, &["a", "b"], tree.root), Some(2));
+        assert_eq!(index_of_node_at_path(&tree.arena, &["a", "b", "c"], tree.root), Some(3));
     }
 
     #[test]
     fn it_gets_a_node_at_a_path() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        tree.add_path(&mut path);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        tree.add_path(&path);
 
-        match tree.get_node_at_path_mut(&mut ["a", "b"]) {
+        match tree.get_node_at_path_mut(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => node.value = 42,
         }
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 42),
         }
@@ -281,79 +499,213 @@ mod tests {
 
     #[test]
     fn it_walks_ancestors() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        let c_index = tree.add_path(&mut path);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        let c_index = tree.add_path(&path);
 
-        match tree.get_node_at_path(&mut ["a", "b", "c"]) {
+        match tree.get_node_at_path(&["a", "b", "c"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        match tree.get_node_at_path(&mut ["a"]) {
+        match tree.get_node_at_path(&["a"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        tree.walk_ancestors(c_index, |node| node.value += 1);
+        tree.walk_ancestors(c_index, |node| node.value += 1).unwrap();
 
-        match tree.get_node_at_path(&mut ["a"]) {
+        match tree.get_node_at_path(&["a"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b", "c"]) {
+        match tree.get_node_at_path(&["a", "b", "c"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
     }
 
+    #[test]
+    fn it_walks_paths_in_order() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["b", "c"], 2);
+        tree.add_value_at_path(&["a"], 1);
+        tree.add_value_at_path(&["b", "a"], 3);
+
+        let mut visited = vec![];
+        tree.walk(|path, node| visited.push((path.join(":"), node.value)));
+
+        let expected = vec![("a", 1), ("b", 0), ("b:a", 3), ("b:c", 2)];
+        let expected: Vec<(String, usize)> = expected.into_iter().map(|(p, v)| (p.to_owned(), v)).collect();
+        assert_eq!(visited, expected);
+    }
+
     #[test]
     fn it_walks_descendants() {
-        let mut tree: Tree<'_, usize> = Tree::new();
-        let mut path = vec!["a", "b", "c"];
-        tree.add_path(&mut path);
+        let mut tree: Tree<usize> = Tree::new();
+        let path = vec!["a", "b", "c"];
+        tree.add_path(&path);
 
-        match tree.get_node_at_path(&mut ["a", "b", "c"]) {
+        match tree.get_node_at_path(&["a", "b", "c"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
-        match tree.get_node_at_path(&mut ["a"]) {
+        match tree.get_node_at_path(&["a"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 0),
         }
 
         tree.walk_descendants(0, |node| node.value += 1);
 
-        match tree.get_node_at_path(&mut ["a"]) {
+        match tree.get_node_at_path(&["a"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b"]) {
+        match tree.get_node_at_path(&["a", "b"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
 
-        match tree.get_node_at_path(&mut ["a", "b", "c"]) {
+        match tree.get_node_at_path(&["a", "b", "c"]) {
             None => panic!("failed to get node at path"),
             Some(node) => assert_eq!(node.value, 1),
         }
     }
+
+    #[test]
+    fn it_removes_subtrees() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b", "c"], 1);
+        tree.add_value_at_path(&["a", "d"], 2);
+        let b = tree.add_path(&["a", "b"]);
+
+        tree.remove_subtree(b).unwrap();
+
+        assert!(tree.get_node_at_path(&["a", "b"]).is_none());
+        assert!(tree.get_node_at_path(&["a", "b", "c"]).is_none());
+        assert!(matches!(tree.remove_subtree(b), Err(Error::NodeNotPresent)));
+        assert!(matches!(tree.remove_subtree(tree.root()), Err(Error::RootNotRemovable)));
+        assert_eq!(tree.children(1), vec![("d", 4)]);
+    }
+
+    #[test]
+    fn it_prunes_nodes_whose_subtrees_fail() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b", "c"], 1);
+        tree.add_value_at_path(&["a", "d"], 0);
+        tree.add_value_at_path(&["e"], 0);
+
+        tree.prune(|_, node| node.value != 0);
+
+        let mut paths = vec![];
+        tree.walk(|path, _| paths.push(path.join(":")));
+        assert_eq!(paths, vec!["a", "a:b", "a:b:c"]);
+    }
+
+    #[test]
+    fn it_prunes_by_path() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_path(&["a", "b", "c"]);
+        tree.add_path(&["d"]);
+
+        tree.prune(|path, _| path.len() <= 2);
+
+        let mut paths = vec![];
+        tree.walk(|path, _| paths.push(path.join(":")));
+        assert_eq!(paths, vec!["a", "a:b", "d"]);
+    }
+
+    #[test]
+    fn it_compacts_the_arena() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b"], 1);
+        tree.add_value_at_path(&["c", "d"], 2);
+        let a = tree.add_path(&["a"]);
+        tree.remove_subtree(a).unwrap();
+
+        let new_indices = tree.compact();
+
+        assert_eq!(new_indices, vec![Some(0), None, None, Some(1), Some(2)]);
+        assert_eq!(tree.arena.len(), 3);
+        assert_eq!(tree.get_node_at_path(&["c", "d"]).map(|n| n.value), Some(2));
+        assert_eq!(tree.get_node_at_index(2).unwrap().parent, Some(1));
+    }
+
+    #[test]
+    fn it_copies_a_subtree() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b"], 1);
+        tree.add_value_at_path(&["a", "b", "c"], 2);
+        tree.add_value_at_path(&["a", "d"], 3);
+
+        let subtree = tree.subtree(&["a", "b"]).unwrap();
+
+        assert_eq!(subtree.get_node_at_index(subtree.root()).unwrap().value, 1);
+        assert_eq!(subtree.get_node_at_path(&["c"]).map(|n| n.value), Some(2));
+        assert!(subtree.get_node_at_path(&["d"]).is_none());
+        assert!(tree.subtree(&["x"]).is_none());
+    }
+
+    #[test]
+    fn it_aggregates_values_up_the_tree() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b", "c"], 1);
+        tree.add_value_at_path(&["a", "b"], 2);
+        tree.add_value_at_path(&["a", "d"], 4);
+        tree.add_value_at_path(&["e"], 8);
+
+        tree.aggregate(|total, value| *total += value);
+
+        let value = |path: &[&str]| tree.get_node_at_path(path).unwrap().value;
+        assert_eq!(value(&["a", "b", "c"]), 1);
+        assert_eq!(value(&["a", "b"]), 3);
+        assert_eq!(value(&["a"]), 7);
+        assert_eq!(value(&[]), 15);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn it_aggregates_values_in_parallel() {
+        let mut tree: Tree<usize> = Tree::new();
+        for i in 0..100 {
+            let name = i.to_string();
+            tree.add_value_at_path(&["a", &name, "b"], i);
+        }
+
+        let mut sequential = tree.subtree(&[]).unwrap();
+        sequential.aggregate(|total, value| *total += value);
+        tree.par_aggregate(|total, value| *total += value);
+
+        assert_eq!(tree.get_node_at_path(&["a"]).unwrap().value, 4950);
+        for (_, _, path, node) in tree.pre_order() {
+            assert_eq!(node.value, sequential.get_node_at_path(&path).unwrap().value);
+        }
+    }
+
+    #[test]
+    fn it_displays_with_closures_that_capture_by_move() {
+        let mut tree: Tree<usize> = Tree::new();
+        tree.add_value_at_path(&["a", "b"], 1);
+
+        let unit = String::from("items");
+        tree.display(&None, move |node| Some(format!("{} {}", node.value, unit)));
+    }
 }